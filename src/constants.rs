@@ -0,0 +1,105 @@
+use std::f64::consts::PI;
+
+use super::types::*;
+
+/// Physical constants are written with this namespace, `const.c`, so that they
+/// never clash with a program's own variables.
+pub const NAMESPACE: &str = "const";
+
+/// Every built in constant as `(name, value)`, with CODATA 2018 values. The
+/// constants which define the SI (`c`, `h`, `k_B`, `N_A`, `e`) are exact.
+pub fn constants() -> Vec<(&'static str, Value)> {
+    let length = Dimension::length;
+    let mass = Dimension::mass;
+    let time = Dimension::time;
+    let energy = || mass() * length().powi(2) / time().powi(2);
+    let constant = |value, dimension| Value::from_si(Magnitude::Scalar(value), dimension);
+
+    let planck = 6.62607015e-34;
+    let boltzmann = 1.380649e-23;
+    let avogadro = 6.02214076e23;
+
+    vec![
+        ("c", constant(299792458.0, length() / time())),
+        (
+            "G",
+            constant(6.67430e-11, length().powi(3) / (mass() * time().powi(2))),
+        ),
+        ("h", constant(planck, energy() * time())),
+        ("hbar", constant(planck / (2.0 * PI), energy() * time())),
+        (
+            "k_B",
+            constant(boltzmann, energy() / Dimension::temperature()),
+        ),
+        ("N_A", constant(avogadro, Dimension::amount().powi(-1))),
+        (
+            "e",
+            constant(1.602176634e-19, Dimension::current() * time()),
+        ),
+        ("g0", constant(9.80665, length() / time().powi(2))),
+        (
+            "R",
+            constant(
+                boltzmann * avogadro,
+                energy() / (Dimension::amount() * Dimension::temperature()),
+            ),
+        ),
+        (
+            "sigma",
+            constant(
+                5.670374419e-8,
+                energy() / (time() * length().powi(2) * Dimension::temperature().powi(4)),
+            ),
+        ),
+    ]
+}
+
+/// Whether `name` is in the constants' namespace, e.g. `const.c`.
+pub fn is_qualified(name: &str) -> bool {
+    name.strip_prefix(NAMESPACE)
+        .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// The qualified name of every constant, e.g. `const.c`.
+pub fn names() -> Vec<String> {
+    constants()
+        .into_iter()
+        .map(|(name, _)| format!("{}.{}", NAMESPACE, name))
+        .collect()
+}
+
+/// Finds a constant by its qualified name, e.g. `const.k_B`.
+pub fn lookup(name: &str) -> Option<Value> {
+    let name = name.strip_prefix(NAMESPACE)?.strip_prefix('.')?;
+    constants()
+        .into_iter()
+        .find(|(constant, _)| *constant == name)
+        .map(|(_, value)| value)
+}
+
+#[test]
+fn test_constants() {
+    let magnitude = |name| match lookup(name).unwrap().magnitude {
+        Magnitude::Scalar(value) => value,
+        Magnitude::Vector(_) => panic!("{} is a vector", name),
+    };
+
+    assert_eq!(lookup("const.c").unwrap().to_string(), "299792458 m/s");
+    assert_eq!(
+        lookup("const.G").unwrap().dimension.to_string(),
+        "m^3/(kg*s^2)"
+    );
+    assert_eq!(lookup("const.h").unwrap().dimension.to_string(), "kg*m^2/s");
+    assert_eq!(lookup("const.e").unwrap().dimension.to_string(), "s*A");
+    assert_eq!(lookup("const.g0").unwrap().to_string(), "9.80665 m/s^2");
+    assert_eq!(
+        lookup("const.sigma").unwrap().dimension.to_string(),
+        "kg/(s^3*K^4)"
+    );
+    assert!((magnitude("const.R") - 8.314462618).abs() < 1e-9);
+    assert!((magnitude("const.hbar") - 1.054571817e-34).abs() < 1e-43);
+
+    assert_eq!(lookup("c"), None);
+    assert_eq!(lookup("const.pi"), None);
+    assert!(is_qualified("const.pi") && !is_qualified("constant"));
+}
@@ -0,0 +1,149 @@
+use std::fmt;
+
+use nom::error::{ErrorKind, ParseError};
+
+use super::types::{Dimension, DimensionMismatch, UnitExpression};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CrunchError {
+    /// The input could not be parsed; `input` is the text at which parsing stopped.
+    Parse {
+        input: String,
+        message: String,
+    },
+    /// A unit alias which is not in the registry, with the nearest known
+    /// aliases, usually one.
+    UnknownUnit {
+        unit: String,
+        suggestions: Vec<String>,
+    },
+    UnknownDimension(String),
+    /// A `dimension` or `unit` declaration which clashes with an earlier one.
+    Declaration(String),
+    /// A variable which has not been assigned, with the nearest known name.
+    UndefinedVariable {
+        name: String,
+        suggestion: Option<String>,
+    },
+    /// An assignment to a physical constant without `override`, or an
+    /// override of a constant which does not exist.
+    Constant(String),
+    DimensionMismatch(Box<DimensionMismatch>),
+    /// A value was converted to a unit of a different dimension.
+    Conversion {
+        from: Dimension,
+        to: UnitExpression,
+    },
+    /// Absolute temperatures used where only differences make sense, e.g.
+    /// `(1[degC] + 2[degC])`.
+    Temperature(String),
+    /// An unknown function, or one called with the wrong number or kind of
+    /// arguments, e.g. `sin(3[m])`.
+    Function(String),
+    /// A power with an exponent which has a dimension, or which is not a
+    /// constant fraction although the base has units.
+    Exponent(String),
+    /// A result which is not a finite real number, e.g. of `acos(2)`.
+    Domain(String),
+    LengthMismatch {
+        lhs: usize,
+        rhs: usize,
+    },
+    DivisionByZero,
+    /// An AST node which cannot be evaluated as an expression.
+    Unsupported(String),
+    /// An error raised while evaluating the expression assigned to `variable`.
+    Evaluation {
+        variable: String,
+        error: Box<CrunchError>,
+    },
+}
+
+pub const POWER_OUT_OF_RANGE: &str = "unit power out of range";
+
+impl CrunchError {
+    pub fn parse(input: &str, message: &str) -> Self {
+        CrunchError::Parse {
+            input: input.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    /// The power of a unit grew beyond what a `Rational` holds, e.g. in
+    /// `(1[m] ^ 2000000000) ^ 2`.
+    pub fn power_out_of_range() -> Self {
+        CrunchError::Exponent(POWER_OUT_OF_RANGE.to_string())
+    }
+}
+
+/// Writes e.g. ", did you mean `usgal` or `impgal`?", or nothing without
+/// suggestions.
+fn write_suggestions(f: &mut fmt::Formatter, suggestions: &[String]) -> fmt::Result {
+    if suggestions.is_empty() {
+        return Ok(());
+    }
+    let quoted: Vec<String> = suggestions
+        .iter()
+        .map(|suggestion| format!("`{}`", suggestion))
+        .collect();
+    write!(f, ", did you mean {}?", quoted.join(" or "))
+}
+
+impl fmt::Display for CrunchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CrunchError::Parse { input, message } => match input.lines().next() {
+                Some(line) if !line.is_empty() => {
+                    write!(f, "parse error: {} at `{}`", message, line)
+                }
+                _ => write!(f, "parse error: {} at end of input", message),
+            },
+            CrunchError::UnknownUnit { unit, suggestions } => {
+                write!(f, "unknown unit `{}`", unit)?;
+                write_suggestions(f, suggestions)
+            }
+            CrunchError::UnknownDimension(name) => write!(f, "unknown dimension `{}`", name),
+            CrunchError::Declaration(message) => write!(f, "{}", message),
+            CrunchError::UndefinedVariable { name, suggestion } => {
+                write!(f, "undefined variable `{}`", name)?;
+                write_suggestions(f, suggestion.as_slice())
+            }
+            CrunchError::Constant(message) => write!(f, "{}", message),
+            CrunchError::DimensionMismatch(error) => write!(f, "{}", error),
+            CrunchError::Conversion { from, to } => {
+                write!(f, "cannot convert {} to [{}]", from.describe(), to)
+            }
+            CrunchError::Temperature(message) => write!(f, "{}", message),
+            CrunchError::Function(message) => write!(f, "{}", message),
+            CrunchError::Exponent(message) => write!(f, "{}", message),
+            CrunchError::Domain(message) => write!(f, "{}", message),
+            CrunchError::LengthMismatch { lhs, rhs } => {
+                write!(f, "cannot combine vectors of length {} and {}", lhs, rhs)
+            }
+            CrunchError::DivisionByZero => write!(f, "division by zero"),
+            CrunchError::Unsupported(node) => write!(f, "cannot evaluate {}", node),
+            CrunchError::Evaluation { variable, error } => {
+                write!(f, "in `{}`: {}", variable, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CrunchError {}
+
+impl From<DimensionMismatch> for CrunchError {
+    fn from(error: DimensionMismatch) -> Self {
+        CrunchError::DimensionMismatch(Box::new(error))
+    }
+}
+
+/// Lets the nom parsers report `CrunchError`s directly.
+impl ParseError<&str> for CrunchError {
+    fn from_error_kind(input: &str, _kind: ErrorKind) -> Self {
+        CrunchError::parse(input, "unexpected input")
+    }
+
+    fn append(_input: &str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
@@ -0,0 +1,162 @@
+use super::error::CrunchError;
+use super::suggest;
+use super::types::*;
+
+/// Every built in function with the number of arguments it takes.
+pub const FUNCTIONS: [(&str, usize); 8] = [
+    ("abs", 1),
+    ("sin", 1),
+    ("cos", 1),
+    ("tan", 1),
+    ("asin", 1),
+    ("acos", 1),
+    ("atan2", 2),
+    ("drop_angle", 1),
+];
+
+fn is_angle(dimension: &Dimension) -> bool {
+    dimension.is_dimensionless() || *dimension == Dimension::angle()
+}
+
+/// The dimension `function` returns for arguments of the given dimensions.
+///
+/// The trigonometric functions take angles, or plain numbers read as radians,
+/// and the inverse functions return angles. `drop_angle` turns e.g. the
+/// `m*rad` of an arc length into `m`, and `abs` keeps the unit.
+pub fn result_dimension(function: &str, arguments: &[Dimension]) -> Result<Dimension, CrunchError> {
+    let error = |message: String| Err(CrunchError::Function(message));
+
+    match (function, arguments) {
+        ("sin" | "cos" | "tan", [argument]) if is_angle(argument) => Ok(Dimension::dimensionless()),
+        ("sin" | "cos" | "tan", [argument]) => error(format!(
+            "`{}` takes an angle or a dimensionless number, not {}",
+            function,
+            argument.describe()
+        )),
+        ("asin" | "acos", [argument]) if argument.is_dimensionless() => Ok(Dimension::angle()),
+        ("asin" | "acos", [argument]) => error(format!(
+            "`{}` takes a dimensionless number, not {}",
+            function,
+            argument.describe()
+        )),
+        ("atan2", [y, x]) if y == x => Ok(Dimension::angle()),
+        ("atan2", [y, x]) => error(format!(
+            "`atan2` takes two values of the same dimension, not {} and {}",
+            y.describe(),
+            x.describe()
+        )),
+        ("abs", [argument]) => Ok(argument.clone()),
+        ("drop_angle", [argument]) => Ok(argument.clone().without_angle()),
+        _ => match FUNCTIONS.iter().find(|(name, _)| *name == function) {
+            Some((_, arity)) => error(format!(
+                "`{}` takes {} argument(s), not {}",
+                function,
+                arity,
+                arguments.len()
+            )),
+            None => {
+                let message = format!("unknown function `{}`", function);
+                match suggest::closest(function, FUNCTIONS.map(|(name, _)| name)) {
+                    Some(suggestion) => {
+                        error(format!("{}, did you mean `{}`?", message, suggestion))
+                    }
+                    None => error(message),
+                }
+            }
+        },
+    }
+}
+
+/// Applies `function` to its evaluated arguments, elementwise for vectors.
+pub fn call(function: &str, arguments: Vec<Value>) -> Result<Value, CrunchError> {
+    let dimensions: Vec<Dimension> = arguments
+        .iter()
+        .map(|argument| argument.dimension.clone())
+        .collect();
+    let dimension = result_dimension(function, &dimensions)?;
+
+    if let ("abs", [argument]) = (function, arguments.as_slice()) {
+        // keeps the unit the argument is shown in, and whether it is absolute
+        return Ok(Value {
+            magnitude: argument.magnitude.clone().map(f64::abs),
+            ..argument.clone()
+        });
+    }
+
+    let mut magnitudes = arguments.into_iter().map(|argument| argument.magnitude);
+    let argument = magnitudes.next().expect("every function takes an argument");
+    let magnitude = match function {
+        "sin" => argument.map(f64::sin),
+        "cos" => argument.map(f64::cos),
+        "tan" => argument.map(f64::tan),
+        "asin" => argument.map(f64::asin),
+        "acos" => argument.map(f64::acos),
+        "atan2" => argument.broadcast(magnitudes.next().unwrap(), f64::atan2)?,
+        _ => argument,
+    };
+    Ok(Value::from_si(magnitude, dimension))
+}
+
+#[test]
+fn test_functions() {
+    let degrees = Unit::new(std::f64::consts::PI / 180.0, Dimension::angle());
+    let meters = Unit::new(1.0, Dimension::length());
+    let number = |value| Value::new(value, Unit::new(1.0, Dimension::dimensionless()));
+
+    assert_eq!(
+        call("sin", vec![Value::new(90.0, degrees.clone())]),
+        Ok(number(1.0))
+    );
+    assert_eq!(call("cos", vec![number(0.0)]), Ok(number(1.0)));
+    assert_eq!(
+        call("asin", vec![number(1.0)]).map(|angle| angle.to_string()),
+        Ok("1.5707963267948966 rad".to_string())
+    );
+    assert_eq!(
+        call(
+            "atan2",
+            vec![
+                Value::new(1.0, meters.clone()),
+                Value::new(1.0, meters.clone())
+            ]
+        )
+        .map(|angle| angle.to_string()),
+        Ok("0.7853981633974483 rad".to_string())
+    );
+    assert_eq!(
+        call(
+            "drop_angle",
+            vec![Value::new(
+                2.0,
+                meters.clone() * Unit::new(1.0, Dimension::angle())
+            )]
+        ),
+        Ok(Value::new(2.0, meters.clone()))
+    );
+    assert_eq!(
+        call("abs", vec![Value::new_vec(vec![-2.0, 3.0], meters.clone())]),
+        Ok(Value::new_vec(vec![2.0, 3.0], meters.clone()))
+    );
+
+    let error = |function, arguments| call(function, arguments).unwrap_err().to_string();
+    assert_eq!(
+        error("sin", vec![Value::new(3.0, meters.clone())]),
+        "`sin` takes an angle or a dimensionless number, not Length (m)"
+    );
+    assert_eq!(
+        error("acos", vec![Value::new(3.0, degrees)]),
+        "`acos` takes a dimensionless number, not Angle (rad)"
+    );
+    assert_eq!(
+        error("atan2", vec![Value::new(1.0, meters), number(1.0)]),
+        "`atan2` takes two values of the same dimension, not Length (m) and Dimensionless (1)"
+    );
+    assert_eq!(
+        error("atan2", vec![number(1.0)]),
+        "`atan2` takes 2 argument(s), not 1"
+    );
+    assert_eq!(
+        error("sine", vec![number(1.0)]),
+        "unknown function `sine`, did you mean `sin`?"
+    );
+}
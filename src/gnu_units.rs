@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while};
+use nom::character::complete::{char, digit1, satisfy, space0, space1};
+use nom::combinator::{map, map_opt, opt, recognize, verify};
+use nom::multi::many0;
+use nom::sequence::{delimited, pair, preceded};
+use nom::IResult;
+
+use super::error::CrunchError;
+use super::parser::parse_decimal;
+use super::rational::Rational;
+use super::types::*;
+use super::units::{UnitDefinition, UnitRegistry};
+
+/// The right hand side of a GNU `units` definition, e.g. `1|8 mile` or
+/// `kg m / s^2`.
+#[derive(PartialEq, Debug, Clone)]
+enum Term {
+    Number(f64),
+    Name(String),
+    Product(Box<Term>, Box<Term>),
+    Quotient(Box<Term>, Box<Term>),
+    Power(Box<Term>, Rational),
+}
+
+/// A number, where `|` divides with the highest precedence: `1|8`.
+fn number(input: &str) -> IResult<&str, Term> {
+    let (input, numerator) = parse_decimal(input)?;
+    let (input, denominator) = opt(preceded(char('|'), parse_decimal))(input)?;
+
+    Ok((input, Term::Number(numerator / denominator.unwrap_or(1.0))))
+}
+
+fn name(input: &str) -> IResult<&str, Term> {
+    let name = recognize(pair(
+        satisfy(|c| c.is_alphabetic() || c == '_'),
+        take_while(|c: char| c.is_alphanumeric() || c == '_'),
+    ));
+
+    map(verify(name, |name: &str| name != "per"), |name: &str| {
+        Term::Name(name.to_string())
+    })(input)
+}
+
+/// `2`, `-1` or `1|2`.
+fn fraction(input: &str) -> IResult<&str, Rational> {
+    map_opt(
+        pair(
+            recognize(pair(opt(char('-')), digit1)),
+            opt(preceded(char('|'), digit1)),
+        ),
+        |(numerator, denominator): (&str, Option<&str>)| {
+            let denominator = denominator.map_or(Some(1), |digits| digits.parse().ok())?;
+            Rational::new(numerator.parse().ok()?, denominator)
+        },
+    )(input)
+}
+
+fn exponent(input: &str) -> IResult<&str, Rational> {
+    preceded(
+        delimited(space0, alt((tag("^"), tag("**"))), space0),
+        alt((
+            fraction,
+            delimited(pair(char('('), space0), fraction, pair(space0, char(')'))),
+        )),
+    )(input)
+}
+
+fn factor(input: &str) -> IResult<&str, Term> {
+    let (input, base) = alt((
+        number,
+        name,
+        delimited(pair(char('('), space0), term, pair(space0, char(')'))),
+    ))(input)?;
+    let (input, power) = opt(exponent)(input)?;
+
+    let factor = match power {
+        Some(power) => Term::Power(Box::new(base), power),
+        None => base,
+    };
+    Ok((input, factor))
+}
+
+/// Multiplication by juxtaposition, `kg m`, which binds tighter than `*`
+/// and `/`.
+fn juxtaposition(input: &str) -> IResult<&str, Term> {
+    let (input, first) = factor(input)?;
+    let (input, rest) = many0(preceded(space0, factor))(input)?;
+
+    let product = rest.into_iter().fold(first, |lhs, rhs| {
+        Term::Product(Box::new(lhs), Box::new(rhs))
+    });
+    Ok((input, product))
+}
+
+fn product(input: &str) -> IResult<&str, Term> {
+    let (input, first) = juxtaposition(input)?;
+    let (input, rest) = many0(pair(
+        delimited(space0, alt((char('*'), char('/'))), space0),
+        juxtaposition,
+    ))(input)?;
+
+    let product = rest
+        .into_iter()
+        .fold(first, |lhs, (operator, rhs)| match operator {
+            '*' => Term::Product(Box::new(lhs), Box::new(rhs)),
+            _ => Term::Quotient(Box::new(lhs), Box::new(rhs)),
+        });
+    Ok((input, product))
+}
+
+/// `per` divides with the lowest precedence.
+fn term(input: &str) -> IResult<&str, Term> {
+    let (input, first) = product(input)?;
+    let (input, rest) = many0(preceded(delimited(space1, tag("per"), space1), product))(input)?;
+
+    let quotient = rest.into_iter().fold(first, |lhs, rhs| {
+        Term::Quotient(Box::new(lhs), Box::new(rhs))
+    });
+    Ok((input, quotient))
+}
+
+fn parse_term(definition: &str) -> Option<Term> {
+    match delimited(space0, term, space0)(definition) {
+        Ok(("", term)) => Some(term),
+        _ => None,
+    }
+}
+
+/// Whether `name` can be written in a crunch unit annotation.
+fn is_symbol(name: &str) -> bool {
+    name.chars().all(|c| c.is_alphabetic() || c == '_')
+}
+
+/// Works out the definitions of a file, which may refer to each other in any
+/// order, to the registry's units and to the file's own prefixes.
+struct Resolver<'a> {
+    registry: &'a UnitRegistry,
+    definitions: HashMap<String, String>,
+    /// Sorted longest first, so that `da` is tried before `d`.
+    prefixes: Vec<(String, String)>,
+    resolved: HashMap<String, Option<Unit>>,
+    resolving: Vec<String>,
+}
+
+impl Resolver<'_> {
+    fn resolve(&mut self, name: &str) -> Option<Unit> {
+        if let Some(unit) = self.resolved.get(name) {
+            return unit.clone();
+        }
+        // a definition which refers back to itself has no meaning
+        if self.resolving.iter().any(|resolving| resolving == name) {
+            return None;
+        }
+
+        self.resolving.push(name.to_string());
+        let unit = self.lookup(name);
+        self.resolving.pop();
+        self.resolved.insert(name.to_string(), unit.clone());
+        unit
+    }
+
+    /// Tries, in order, the file's definitions, the registry, a plural, a
+    /// prefixed unit and a power written as a trailing digit, `cm3`.
+    fn lookup(&mut self, name: &str) -> Option<Unit> {
+        if let Some(definition) = self.definitions.get(name).cloned() {
+            return match definition.as_str() {
+                "!" => self.registry.lookup(name).map(relative),
+                // `radian` is dimensionless to GNU units, but an angle here
+                "!dimensionless" => Some(
+                    self.registry
+                        .lookup(name)
+                        .map(relative)
+                        .unwrap_or_else(|| Unit::new(1.0, Dimension::dimensionless())),
+                ),
+                _ => self.evaluate(&parse_term(&definition)?),
+            };
+        }
+        if let Some(unit) = self.registry.lookup(name) {
+            return Some(relative(unit));
+        }
+
+        for (suffix, replacement) in [("ies", "y"), ("es", ""), ("s", "")] {
+            if let Some(stem) = name.strip_suffix(suffix).filter(|stem| !stem.is_empty()) {
+                if let Some(unit) = self.resolve(&format!("{}{}", stem, replacement)) {
+                    return Some(unit);
+                }
+            }
+        }
+
+        for (prefix, definition) in self.prefixes.clone() {
+            let Some(rest) = name.strip_prefix(prefix.as_str()) else {
+                continue;
+            };
+            let Some(factor) = parse_term(&definition).and_then(|term| self.evaluate(&term)) else {
+                continue;
+            };
+            // a prefix on its own is a number, `kilo` is 1000
+            if rest.is_empty() {
+                return Some(factor);
+            }
+            if let Some(unit) = self.resolve(rest) {
+                return factor.checked_mul(unit);
+            }
+        }
+
+        let power = name
+            .chars()
+            .last()?
+            .to_digit(10)
+            .filter(|power| *power >= 2)?;
+        self.resolve(&name[..name.len() - 1])
+            .and_then(|unit| unit.checked_pow(Rational::from(power as i32)))
+    }
+
+    fn evaluate(&mut self, term: &Term) -> Option<Unit> {
+        match term {
+            Term::Number(number) => Some(Unit::new(*number, Dimension::dimensionless())),
+            Term::Name(name) => self.resolve(name),
+            Term::Product(lhs, rhs) => self.evaluate(lhs)?.checked_mul(self.evaluate(rhs)?),
+            Term::Quotient(lhs, rhs) => self
+                .evaluate(lhs)?
+                .checked_mul(self.evaluate(rhs)?.checked_pow(Rational::from(-1))?),
+            Term::Power(base, power) => self.evaluate(base)?.checked_pow(*power),
+        }
+    }
+}
+
+/// Inside a definition a temperature scale such as `K` means a difference.
+fn relative(unit: Unit) -> Unit {
+    Unit::new(unit.conversion_factor, unit.dimension)
+}
+
+/// Joins lines continued with a trailing `\` and drops `#` comments and the
+/// sections which only apply to some locales or environment variables.
+fn definition_lines(source: &str) -> Vec<String> {
+    let mut lines = vec![];
+    let mut continued = String::new();
+    let mut conditional_depth = 0;
+
+    for line in source.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        if let Some(start) = line.strip_suffix('\\') {
+            continued.push_str(start);
+            continued.push(' ');
+            continue;
+        }
+        continued.push_str(line);
+        let line = std::mem::take(&mut continued);
+
+        let directive = line.split_whitespace().next().unwrap_or_default();
+        match directive {
+            "!locale" | "!var" | "!varnot" => conditional_depth += 1,
+            "!endlocale" | "!endvar" => conditional_depth -= 1,
+            _ if conditional_depth > 0 || directive.starts_with('!') => {}
+            "" => {}
+            _ => lines.push(line.trim().to_string()),
+        }
+    }
+    lines
+}
+
+/// Imports the units of a file written for GNU `units`, such as its
+/// `definitions.units`.
+///
+/// Units which the registry already knows keep their meaning. A primitive
+/// unit, `bit !`, which the registry does not know becomes the base unit of
+/// a new dimension of the same name. Prefixes are only used to understand
+/// the file's own definitions, and functions, tables and unit lists are not
+/// imported. Returns the names of the definitions which could not be
+/// imported.
+pub fn import(registry: &mut UnitRegistry, source: &str) -> Result<Vec<String>, CrunchError> {
+    let mut names = vec![];
+    let mut definitions = HashMap::new();
+    let mut prefixes = vec![];
+    let mut skipped = vec![];
+
+    for line in definition_lines(source) {
+        let (name, definition) = match line.split_once(char::is_whitespace) {
+            Some((name, definition)) => (name, definition.trim()),
+            None => (line.as_str(), ""),
+        };
+
+        if let Some(prefix) = name.strip_suffix('-') {
+            prefixes.push((prefix.to_string(), definition.to_string()));
+        } else if definition.is_empty()
+            || name.contains(['(', '['])
+            || definition.contains(';')
+            || definitions.contains_key(name)
+        {
+            skipped.push(name.to_string());
+        } else {
+            names.push(name.to_string());
+            definitions.insert(name.to_string(), definition.to_string());
+        }
+    }
+    prefixes.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+
+    for name in &names {
+        if definitions[name] == "!" && is_symbol(name) && registry.lookup(name).is_none() {
+            registry.declare_dimension(name)?;
+            registry.declare_base_unit(name, name, false)?;
+        }
+    }
+
+    let mut resolver = Resolver {
+        registry,
+        definitions,
+        prefixes,
+        resolved: HashMap::new(),
+        resolving: vec![],
+    };
+    let mut imported = vec![];
+    for name in names {
+        if resolver.registry.lookup(&name).is_some() {
+            continue;
+        }
+        match resolver.resolve(&name) {
+            Some(unit) if is_symbol(&name) => {
+                imported.push(UnitDefinition::new(&name, &[], unit, false))
+            }
+            _ => skipped.push(name),
+        }
+    }
+
+    for definition in imported {
+        registry.define(definition);
+    }
+    Ok(skipped)
+}
+
+#[test]
+fn test_parse_term() {
+    let name = |name: &str| Box::new(Term::Name(name.to_string()));
+
+    assert_eq!(
+        parse_term("1|8 mile"),
+        Some(Term::Product(Box::new(Term::Number(0.125)), name("mile")))
+    );
+    assert_eq!(
+        parse_term("kg m / s^2"),
+        Some(Term::Quotient(
+            Box::new(Term::Product(name("kg"), name("m"))),
+            Box::new(Term::Power(name("s"), Rational::from(2)))
+        ))
+    );
+    assert_eq!(
+        parse_term("m per s s"),
+        Some(Term::Quotient(
+            name("m"),
+            Box::new(Term::Product(name("s"), name("s")))
+        ))
+    );
+    assert_eq!(
+        parse_term("(ft^3)^(1|2)"),
+        Some(Term::Power(
+            Box::new(Term::Power(name("ft"), Rational::from(3))),
+            Rational::new(1, 2).unwrap()
+        ))
+    );
+    assert_eq!(
+        parse_term("nanometer"),
+        Some(Term::Name("nanometer".to_string()))
+    );
+    assert_eq!(parse_term("tempC(x) units=[1;K]"), None);
+}
+
+#[test]
+fn test_import() {
+    let source = "\
+# primitive units
+m       !
+kg      !
+s       !
+bit     !
+radian  !dimensionless
+
+kilo-   1e3
+k-      kilo
+
+inch    2.54 cm
+foot    12 inch
+feet    foot
+mile    5280 ft
+furlong 1|8 mile
+stone   14 lb
+acre    43560 \\
+        feet^2
+hogshead 63 gallon
+byte    8 bit
+kibibyte 1024 byte
+turn    2 pi radian
+revolution turn
+rpm     revolution / minute
+pi      3.14159265358979323846
+fortnight 14 day
+newton  kg m / s^2
+tempF(x) units=[1;K] (x+(-32)) degF + stdtemp
+!locale en_GB
+hundredweight 112 pound
+!endlocale
+";
+    let mut registry = UnitRegistry::builtin();
+    let skipped = import(&mut registry, source).unwrap();
+    let factor = |alias| registry.lookup(alias).unwrap().conversion_factor;
+
+    assert_eq!(skipped, vec!["tempF(x)", "hogshead"]);
+    assert!((factor("furlong") - 201.168).abs() < 1e-9);
+    assert!((factor("acre") - 4046.8564224).abs() < 1e-6);
+    assert!((factor("stone") - 6.35029318).abs() < 1e-9);
+    assert_eq!(factor("fortnight"), 14.0 * 86400.0);
+    assert_eq!(factor("turn"), 2.0 * std::f64::consts::PI);
+    assert_eq!(
+        registry
+            .lookup("kibibyte")
+            .map(|unit| unit.dimension.describe()),
+        Some("bit (bit)".to_string())
+    );
+    assert_eq!(factor("kibibyte"), 8192.0);
+
+    // units derived from the radian are angles
+    assert_eq!(
+        registry.lookup("rpm").map(|unit| unit.dimension),
+        Some(Dimension::angle() / Dimension::time())
+    );
+    assert!((factor("rpm") - 2.0 * std::f64::consts::PI / 60.0).abs() < 1e-12);
+
+    // built in units keep their meaning, and locale sections are skipped
+    assert_eq!(
+        registry.lookup("newton"),
+        UnitRegistry::builtin().lookup("N")
+    );
+    assert_eq!(registry.lookup("hundredweight"), None);
+}
@@ -1,4 +1,10 @@
+use super::constants;
+use super::error::CrunchError;
+use super::functions;
+use super::rational::Rational;
+use super::suggest;
 use super::types::*;
+use super::units::UnitRegistry;
 
 use std::clone::Clone;
 use std::collections::HashMap;
@@ -8,140 +14,1095 @@ use std::fmt::Debug;
 pub struct Memory {
     instructions: Vec<Vec<AstNode>>,
     pub memory: HashMap<String, Value>,
+    pub units: UnitRegistry,
+}
+
+impl Memory {
+    /// A program which starts out with `units` rather than the built in units.
+    pub fn with_units(instructions: Vec<Vec<AstNode>>, units: UnitRegistry) -> Self {
+        Memory {
+            instructions,
+            memory: HashMap::new(),
+            units,
+        }
+    }
+
+    /// Runs an assignment, a constant override or a declaration.
+    fn statement(&mut self, statement: &AstNode) -> Result<(), CrunchError> {
+        match assigned(statement)? {
+            Some((name, expression)) => self.assign(name, expression),
+            None => self.declare(statement),
+        }
+    }
+
+    /// Declares a base dimension or a unit in the unit registry.
+    fn declare(&mut self, declaration: &AstNode) -> Result<(), CrunchError> {
+        let (name, result) = match declaration {
+            AstNode::DimensionDeclaration(name) => (name, self.units.declare_dimension(name)),
+            AstNode::BaseUnitDeclaration {
+                name,
+                dimension,
+                prefixable,
+            } => (
+                name,
+                self.units.declare_base_unit(name, dimension, *prefixable),
+            ),
+            AstNode::UnitDeclaration {
+                name,
+                value,
+                prefixable,
+            } => {
+                // a unit means the same thing wherever it is used, and `check`
+                // has no values to evaluate variables with
+                let unit = match first_variable(value) {
+                    Some(variable) => Err(CrunchError::Declaration(format!(
+                        "unit values cannot reference variables such as `{}`",
+                        variable
+                    ))),
+                    None => self.evaluate(*value.clone()).and_then(defined_unit),
+                };
+                (
+                    name,
+                    unit.and_then(|unit| self.units.declare_unit(name, unit, *prefixable)),
+                )
+            }
+            _ => return Err(CrunchError::Unsupported(format!("{:?}", declaration))),
+        };
+        result.map_err(|error| CrunchError::Evaluation {
+            variable: name.clone(),
+            error: Box::new(error),
+        })
+    }
+
+    /// Evaluates one assignment and stores the result.
+    fn assign(&mut self, name: String, expression: &AstNode) -> Result<(), CrunchError> {
+        let unitted_value =
+            self.evaluate(expression.clone())
+                .map_err(|error| CrunchError::Evaluation {
+                    variable: name.clone(),
+                    error: Box::new(error),
+                })?;
+
+        self.memory.insert(name, unitted_value);
+        Ok(())
+    }
+
+    /// The names assigned by the program, in the order they are first assigned.
+    pub fn variables(&self) -> Vec<String> {
+        let mut names: Vec<String> = vec![];
+        for variable in self.instructions.iter().flatten() {
+            if let Ok((name, _)) = assignment(variable) {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    /// Every variable with its current value, in the order they are first assigned.
+    pub fn values(&self) -> Vec<(String, Value)> {
+        self.variables()
+            .into_iter()
+            .filter_map(|name| {
+                let value = self.memory.get(&name)?.clone();
+                Some((name, value))
+            })
+            .collect()
+    }
+
+    /// Like `run`, but an error comes with the index of the line it is in, so
+    /// that it can be reported where the line was read from.
+    pub fn run_lines(&mut self) -> Result<(), (usize, CrunchError)> {
+        for (index, line) in self.instructions.clone().into_iter().enumerate() {
+            for statement in &line {
+                self.statement(statement).map_err(|error| (index, error))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `check`, with the index of the line as `run_lines` has it.
+    pub fn check_lines(&self) -> Result<Vec<(String, Dimension)>, (usize, CrunchError)> {
+        let mut dimensions: HashMap<String, Dimension> = self
+            .memory
+            .iter()
+            .map(|(name, value)| (name.clone(), value.dimension.clone()))
+            .collect();
+
+        // declarations are made in a copy, so that checking changes nothing
+        let mut scope = self.clone();
+        for (index, line) in self.instructions.iter().enumerate() {
+            for statement in line {
+                scope
+                    .check_statement(statement, &mut dimensions)
+                    .map_err(|error| (index, error))?;
+            }
+        }
+
+        Ok(self
+            .variables()
+            .into_iter()
+            .map(|name| {
+                let dimension = dimensions[&name].clone();
+                (name, dimension)
+            })
+            .collect())
+    }
+
+    /// Works out the dimension an assignment stores, or makes a declaration.
+    fn check_statement(
+        &mut self,
+        statement: &AstNode,
+        dimensions: &mut HashMap<String, Dimension>,
+    ) -> Result<(), CrunchError> {
+        let Some((name, expression)) = assigned(statement)? else {
+            return self.declare(statement);
+        };
+        let dimension = self
+            .infer_dimension(expression, dimensions)
+            .map_err(|error| CrunchError::Evaluation {
+                variable: name.clone(),
+                error: Box::new(error),
+            })?;
+        dimensions.insert(name, dimension);
+        Ok(())
+    }
+}
+
+/// The unit a `unit name = value;` declaration defines.
+fn defined_unit(value: Value) -> Result<Unit, CrunchError> {
+    if value.absolute {
+        return Err(CrunchError::Temperature(
+            "cannot define a unit as an absolute temperature".to_string(),
+        ));
+    }
+    match value.magnitude {
+        Magnitude::Scalar(factor) => Ok(Unit::new(factor, value.dimension)),
+        Magnitude::Vector(_) => Err(CrunchError::Unsupported(
+            "a unit defined as a vector".to_string(),
+        )),
+    }
+}
+
+/// The name and expression of an assignment or a constant override, or `None`
+/// for a declaration. Physical constants can only be assigned by an override.
+fn assigned(statement: &AstNode) -> Result<Option<(String, &AstNode)>, CrunchError> {
+    let (name, expression) = match statement {
+        AstNode::Variable { .. } => assignment(statement)?,
+        AstNode::ConstantOverride { name, expr } => (name.clone(), expr.as_ref()),
+        _ => return Ok(None),
+    };
+
+    let overrides = matches!(statement, AstNode::ConstantOverride { .. });
+    let message = match (overrides, constants::lookup(&name).is_some()) {
+        (false, _) if !constants::is_qualified(&name) => return Ok(Some((name, expression))),
+        (true, true) => return Ok(Some((name, expression))),
+        (false, true) => "cannot assign to a physical constant, use `override` to replace it",
+        (_, false) => "there is no such physical constant",
+    };
+    Err(CrunchError::Evaluation {
+        variable: name,
+        error: Box::new(CrunchError::Constant(message.to_string())),
+    })
+}
+
+/// The first variable `expression` reads, if any. Physical constants are not
+/// variables.
+fn first_variable(expression: &AstNode) -> Option<&str> {
+    match expression {
+        AstNode::Name(name) if !constants::is_qualified(name) => Some(name),
+        AstNode::Expression { lhs, rhs, .. } => first_variable(lhs).or_else(|| first_variable(rhs)),
+        AstNode::Call { arguments, .. } => arguments.iter().find_map(first_variable),
+        AstNode::Unary { expr, .. } | AstNode::Conversion { expr, .. } => first_variable(expr),
+        _ => None,
+    }
+}
+
+/// Whether `expression` has the same value whatever the variables hold.
+fn is_constant(expression: &AstNode) -> bool {
+    match expression {
+        AstNode::Double { .. } | AstNode::Vector { .. } => true,
+        AstNode::Expression { lhs, rhs, .. } => is_constant(lhs) && is_constant(rhs),
+        AstNode::Call { arguments, .. } => arguments.iter().all(is_constant),
+        AstNode::Unary { expr, .. } | AstNode::Conversion { expr, .. } => is_constant(expr),
+        _ => false,
+    }
+}
+
+/// Raises `base` to the power `exponent`, which must be dimensionless.
+///
+/// A plain number can be raised to any power, elementwise for vectors. The
+/// dimension of a quantity with units depends on the exponent, so that has to
+/// be a `constant` scalar which is a simple fraction such as `2` or `1/2`.
+fn power(base: Value, exponent: Value, constant: bool) -> Result<Value, CrunchError> {
+    let error = |message: String| Err(CrunchError::Exponent(message));
+
+    if !exponent.dimension.is_dimensionless() {
+        return error(format!(
+            "the exponent must be dimensionless, not {}",
+            exponent.dimension.describe()
+        ));
+    }
+    if base.dimension.is_dimensionless() {
+        return Ok(Value::from_si(
+            base.magnitude.broadcast(exponent.magnitude, f64::powf)?,
+            Dimension::dimensionless(),
+        ));
+    }
+    let Magnitude::Scalar(exponent) = exponent.magnitude else {
+        return error("a quantity with units cannot be raised to a vector exponent".to_string());
+    };
+    if !constant {
+        return error(
+            "a quantity with units can only be raised to a constant exponent, not one computed from variables"
+                .to_string(),
+        );
+    }
+    match Rational::approximate(exponent) {
+        Some(exponent) => base.pow(exponent),
+        None => error(format!(
+            "a quantity with units can only be raised to a simple fraction, not {}",
+            exponent
+        )),
+    }
+}
+
+/// The error for a variable `name` which has not been assigned, suggesting the
+/// nearest of the `assigned` names and the physical constants.
+fn undefined<'a>(name: &str, assigned: impl Iterator<Item = &'a String>) -> CrunchError {
+    let constants = constants::names();
+    let qualified = format!("{}.{}", constants::NAMESPACE, name);
+    let suggestion = match constants.contains(&qualified) {
+        true => Some(qualified),
+        false => {
+            let mut candidates: Vec<&str> = assigned.map(String::as_str).collect();
+            candidates.extend(constants.iter().map(String::as_str));
+            suggest::closest(name, candidates)
+        }
+    };
+    CrunchError::UndefinedVariable {
+        name: name.to_string(),
+        suggestion,
+    }
+}
+
+/// Splits an `AstNode::Variable` into the assigned name and its expression.
+fn assignment(variable: &AstNode) -> Result<(String, &AstNode), CrunchError> {
+    match variable {
+        AstNode::Variable { name, expr } => match name.as_ref() {
+            AstNode::Name(name) => Ok((name.clone(), expr)),
+            _ => Err(CrunchError::Unsupported(format!("{:?}", name))),
+        },
+        _ => Err(CrunchError::Unsupported(format!("{:?}", variable))),
+    }
 }
 
 pub trait Interpreter {
     fn new(instructions: Vec<Vec<AstNode>>) -> Self;
-    fn run(&mut self) -> ();
-    fn evaluate(&self, expression: AstNode) -> Value;
+    fn run(&mut self) -> Result<(), CrunchError>;
+    /// Runs one more line against the existing bindings and keeps it as part
+    /// of the program. Nothing is stored if any statement in the line fails.
+    fn execute(&mut self, line: Vec<AstNode>) -> Result<(), CrunchError>;
+    /// Works out the dimension of every variable without evaluating any values.
+    fn check(&self) -> Result<Vec<(String, Dimension)>, CrunchError>;
+    fn infer_dimension(
+        &self,
+        expression: &AstNode,
+        dimensions: &HashMap<String, Dimension>,
+    ) -> Result<Dimension, CrunchError>;
+    /// Looks a unit annotation up in the unit registry.
+    fn resolve_unit(&self, unit: &UnitExpression) -> Result<Unit, CrunchError>;
+    fn evaluate(&self, expression: AstNode) -> Result<Value, CrunchError>;
     fn evaluate_expression(
         &self,
         operation: BinaryOperation,
         lhs: Box<AstNode>,
         rhs: Box<AstNode>,
-    ) -> Value;
+    ) -> Result<Value, CrunchError>;
 }
 
 impl Interpreter for Memory {
     fn new(instructions: Vec<Vec<AstNode>>) -> Self {
-        Memory {
-            instructions: instructions,
-            memory: HashMap::new(),
-        }
+        Memory::with_units(instructions, UnitRegistry::default())
     }
 
-    fn run(&mut self) -> () {
-        for line in &self.instructions {
-            for variable in line {
-                println!("\nCalculating {:#?}", variable.clone());
-                let name = match variable {
-                    AstNode::Variable { name, expr: _expr } => match *name.clone() {
-                        AstNode::Name(name) => name,
-                        _ => panic!("Variable name should be of type AstNode::Name"),
-                    },
-                    _ => panic!("Variable should be of type AstNode::Variable"),
-                };
-
-                let unitted_value = match variable {
-                    AstNode::Variable {
-                        name: _name,
-                        expr: expression,
-                    } => self.evaluate(*expression.clone()),
-                    _ => panic!("Variable should be of type AstNode::Variable"),
-                };
+    fn run(&mut self) -> Result<(), CrunchError> {
+        self.run_lines().map_err(|(_, error)| error)
+    }
 
-                println!(
-                    "\nStoring result {:#?} = {:#?}",
-                    name.clone(),
-                    unitted_value.clone()
-                );
-                self.memory.insert(name, unitted_value);
-                println!("=================================\n\n");
+    fn execute(&mut self, line: Vec<AstNode>) -> Result<(), CrunchError> {
+        let snapshot = (self.memory.clone(), self.units.clone());
+        for statement in &line {
+            if let Err(error) = self.statement(statement) {
+                (self.memory, self.units) = snapshot;
+                return Err(error);
             }
         }
+        self.instructions.push(line);
+        Ok(())
     }
 
-    fn evaluate(&self, expression: AstNode) -> Value {
+    fn check(&self) -> Result<Vec<(String, Dimension)>, CrunchError> {
+        self.check_lines().map_err(|(_, error)| error)
+    }
+
+    fn infer_dimension(
+        &self,
+        expression: &AstNode,
+        dimensions: &HashMap<String, Dimension>,
+    ) -> Result<Dimension, CrunchError> {
         match expression {
-            AstNode::Name(name) => self.memory[&name].clone(),
-            AstNode::Double { value, unit } => Value::new(value, unit),
-            AstNode::Vector { value, unit } => Value::new_vec(value, unit),
+            AstNode::Name(name) => match dimensions.get(name) {
+                Some(dimension) => Ok(dimension.clone()),
+                None => constants::lookup(name)
+                    .map(|constant| constant.dimension)
+                    .ok_or_else(|| undefined(name, dimensions.keys())),
+            },
+            AstNode::Double { unit, .. } | AstNode::Vector { unit, .. } => {
+                Ok(self.resolve_unit(unit)?.dimension)
+            }
             AstNode::Expression {
                 operation,
                 lhs,
                 rhs,
-            } => self.evaluate_expression(operation, lhs, rhs),
-            _ => panic!("1 Expression should be of type AstNode::Expression, AstNode::Double, AstNode::Vector, or AstNode::Name, found: {:#?}", expression),
+            } => {
+                let exponent = rhs;
+                let lhs = self.infer_dimension(lhs, dimensions)?;
+                let rhs = self.infer_dimension(rhs, dimensions)?;
+                match operation {
+                    BinaryOperation::Add | BinaryOperation::Subtract if lhs != rhs => {
+                        Err(DimensionMismatch {
+                            operation: operation.clone(),
+                            lhs,
+                            rhs,
+                        }
+                        .into())
+                    }
+                    BinaryOperation::Add | BinaryOperation::Subtract => Ok(lhs),
+                    BinaryOperation::Multiply => lhs
+                        .checked_mul(rhs)
+                        .ok_or_else(CrunchError::power_out_of_range),
+                    BinaryOperation::Divide => lhs
+                        .checked_div(rhs)
+                        .ok_or_else(CrunchError::power_out_of_range),
+                    BinaryOperation::Power if lhs.is_dimensionless() && rhs.is_dimensionless() => {
+                        Ok(lhs)
+                    }
+                    BinaryOperation::Power => {
+                        // the dimension depends on the exponent's value, which
+                        // is only known here when it is a constant
+                        let constant = is_constant(exponent);
+                        let exponent = match constant {
+                            true => self.evaluate(*exponent.clone())?,
+                            false => Value::from_si(Magnitude::Scalar(f64::NAN), rhs),
+                        };
+                        let base = Value::from_si(Magnitude::Scalar(1.0), lhs);
+                        Ok(power(base, exponent, constant)?.dimension)
+                    }
+                }
+            }
+            AstNode::Unary { expr, .. } => self.infer_dimension(expr, dimensions),
+            AstNode::Conversion { expr, unit } => {
+                let dimension = self.infer_dimension(expr, dimensions)?;
+                if self.resolve_unit(unit)?.dimension != dimension {
+                    return Err(CrunchError::Conversion {
+                        from: dimension,
+                        to: unit.clone(),
+                    });
+                }
+                Ok(dimension)
+            }
+            AstNode::Call {
+                function,
+                arguments,
+            } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.infer_dimension(argument, dimensions))
+                    .collect::<Result<Vec<Dimension>, CrunchError>>()?;
+                functions::result_dimension(function, &arguments)
+            }
+            _ => Err(CrunchError::Unsupported(format!("{:?}", expression))),
         }
     }
 
-    fn evaluate_expression(
-        &self,
-        operation: BinaryOperation,
-        lhs: Box<AstNode>,
-        rhs: Box<AstNode>,
-    ) -> Value {
-        let lhs_value = match *lhs {
-            AstNode::Name(name) => self.memory[&name].clone(),
-            AstNode::Double { value, unit } => Value::new(value, unit),
-            AstNode::Vector { value, unit } => Value::new_vec(value, unit),
-            AstNode::Expression {
-                operation,
-                lhs,
-                rhs,
-            } => self.evaluate_expression(operation, lhs, rhs),
-            _ => panic!("2 Expression should be of type AstNode::Expression, AstNode::Double, AstNode::Vector, or AstNode::Name, found: {:#?}", lhs),
-        };
+    fn resolve_unit(&self, unit: &UnitExpression) -> Result<Unit, CrunchError> {
+        let mut product: Option<Unit> = None;
+        for (alias, power) in &unit.factors {
+            let Some(resolved) = self.units.lookup(alias) else {
+                return Err(CrunchError::UnknownUnit {
+                    unit: alias.clone(),
+                    suggestions: self.units.suggest(alias),
+                });
+            };
+            // a lone factor keeps the offset of an absolute temperature scale
+            let factor = resolved.checked_pow(*power);
+            let factor = match product {
+                Some(product) => factor.and_then(|factor| product.checked_mul(factor)),
+                None => factor,
+            };
+            product = Some(factor.ok_or_else(CrunchError::power_out_of_range)?);
+        }
+        Ok(product.unwrap_or_else(|| Unit::new(1.0, Dimension::dimensionless())))
+    }
 
-        let rhs_value = match *rhs {
-            AstNode::Name(name) => self.memory[&name].clone(),
-            AstNode::Double { value, unit } => Value::new(value, unit),
-            AstNode::Vector { value, unit } => Value::new_vec(value, unit),
+    fn evaluate(&self, expression: AstNode) -> Result<Value, CrunchError> {
+        let value = match expression {
+            AstNode::Name(name) => match self.memory.get(&name) {
+                Some(value) => Ok(value.clone()),
+                None => {
+                    constants::lookup(&name).ok_or_else(|| undefined(&name, self.memory.keys()))
+                }
+            },
+            AstNode::Double { value, unit } => Ok(Value::new(value, self.resolve_unit(&unit)?)),
+            AstNode::Vector { value, unit } => Ok(Value::new_vec(value, self.resolve_unit(&unit)?)),
             AstNode::Expression {
                 operation,
                 lhs,
                 rhs,
             } => self.evaluate_expression(operation, lhs, rhs),
-            _ => panic!("3 Expression should be of type AstNode::Expression, AstNode::Double, AstNode::Vector, or AstNode::Name, found: {:#?}", rhs),
-        };
+            AstNode::Unary { operation, expr } => match operation {
+                UnaryOperation::Plus => self.evaluate(*expr),
+                UnaryOperation::Minus => {
+                    let value = self.evaluate(*expr)?;
+                    -value
+                }
+            },
+            AstNode::Conversion { expr, unit } => {
+                let resolved = self.resolve_unit(&unit)?;
+                self.evaluate(*expr)?.convert(unit, resolved)
+            }
+            AstNode::Call {
+                function,
+                arguments,
+            } => {
+                let arguments = arguments
+                    .into_iter()
+                    .map(|argument| self.evaluate(argument))
+                    .collect::<Result<Vec<Value>, CrunchError>>()?;
+                functions::call(&function, arguments)
+            }
+            _ => Err(CrunchError::Unsupported(format!("{:?}", expression))),
+        }?;
+        // `f64` arithmetic gives NaN or infinity rather than failing
+        value.finite()
+    }
+
+    fn evaluate_expression(
+        &self,
+        operation: BinaryOperation,
+        lhs: Box<AstNode>,
+        rhs: Box<AstNode>,
+    ) -> Result<Value, CrunchError> {
+        let constant = is_constant(&rhs);
+        let lhs_value = self.evaluate(*lhs)?;
+        let rhs_value = self.evaluate(*rhs)?;
 
         match operation {
             BinaryOperation::Add => lhs_value + rhs_value,
             BinaryOperation::Subtract => lhs_value - rhs_value,
             BinaryOperation::Multiply => lhs_value * rhs_value,
             BinaryOperation::Divide => lhs_value / rhs_value,
+            BinaryOperation::Power => power(lhs_value, rhs_value, constant),
         }
     }
 }
 
+#[cfg(test)]
+use super::parser::parse_line;
+
+/// Runs `line` against the program, which has to fail, and returns the error.
+#[cfg(test)]
+fn execute_error(i: &mut Memory, line: &str) -> String {
+    i.execute(parse_line(line).unwrap())
+        .unwrap_err()
+        .to_string()
+}
+
 #[test]
 fn test_interpreter() {
-    let i: Memory = Interpreter::new(vec![vec![AstNode::Variable {
+    let mut i: Memory = Interpreter::new(vec![vec![AstNode::Variable {
         name: Box::new(AstNode::Name("var".to_string())),
         expr: Box::new(AstNode::Expression {
             operation: BinaryOperation::Divide,
             lhs: Box::new(AstNode::Double {
-                value: Value::Float(2.0),
-                unit: Unit::Length {
-                    unit: Unit {
-                        unit: UnitIdentity::Meter,
-                        conversion_factor: 1.0,
-                    },
-                    power: 1,
-                },
+                value: 2.0,
+                unit: UnitExpression::new("m", 1),
+            }),
+            rhs: Box::new(AstNode::Double {
+                value: 2.0,
+                unit: UnitExpression::new("km", 1),
+            }),
+        }),
+    }]]);
+
+    i.run().unwrap();
+
+    assert_eq!(i.memory["var"].magnitude, Magnitude::Scalar(0.001));
+    assert!(i.memory["var"].dimension.is_dimensionless());
+}
+
+#[test]
+fn test_interpreter_dimension_mismatch() {
+    let mut i: Memory = Interpreter::new(vec![vec![AstNode::Variable {
+        name: Box::new(AstNode::Name("var".to_string())),
+        expr: Box::new(AstNode::Expression {
+            operation: BinaryOperation::Add,
+            lhs: Box::new(AstNode::Double {
+                value: 2.0,
+                unit: UnitExpression::new("m", 2),
             }),
             rhs: Box::new(AstNode::Double {
-                value: Value::Float(2.0),
-                unit: Unit::Length {
-                    unit: Unit {
-                        unit: UnitIdentity::Kilometer,
-                        conversion_factor: 1000.0,
-                    },
-                    power: 1,
-                },
+                value: 2.0,
+                unit: UnitExpression::new("km", 1),
             }),
         }),
     }]]);
 
-    println!("{:#?}", i.memory);
+    let error = i.run().unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "in `var`: cannot add Area (m^2) to Length (m)"
+    );
+    assert!(!i.memory.contains_key("var"));
+}
+
+#[test]
+fn test_interpreter_undefined_variable() {
+    let mut i: Memory = Interpreter::new(vec![vec![AstNode::Variable {
+        name: Box::new(AstNode::Name("var".to_string())),
+        expr: Box::new(AstNode::Name("missing".to_string())),
+    }]]);
+
+    assert_eq!(
+        i.run(),
+        Err(CrunchError::Evaluation {
+            variable: "var".to_string(),
+            error: Box::new(CrunchError::UndefinedVariable {
+                name: "missing".to_string(),
+                suggestion: None
+            }),
+        })
+    );
+}
+
+#[test]
+fn test_interpreter_check() {
+    let meters = UnitExpression::new("m", 1);
+    let i: Memory = Interpreter::new(vec![
+        vec![AstNode::Variable {
+            name: Box::new(AstNode::Name("a".to_string())),
+            expr: Box::new(AstNode::Vector {
+                value: vec![1.0, 2.0],
+                unit: meters,
+            }),
+        }],
+        vec![AstNode::Variable {
+            name: Box::new(AstNode::Name("b".to_string())),
+            expr: Box::new(AstNode::Expression {
+                operation: BinaryOperation::Multiply,
+                lhs: Box::new(AstNode::Name("a".to_string())),
+                rhs: Box::new(AstNode::Name("a".to_string())),
+            }),
+        }],
+    ]);
+
+    assert_eq!(
+        i.check(),
+        Ok(vec![
+            ("a".to_string(), Dimension::length()),
+            ("b".to_string(), Dimension::length().powi(2)),
+        ])
+    );
+    assert!(i.memory.is_empty());
+}
+
+#[test]
+fn test_interpreter_failing_line() {
+    let program = vec![
+        parse_line("a = 2[m];").unwrap(),
+        parse_line("unit furlong = 201.168[m]; b = 1[furlong];").unwrap(),
+        parse_line("c = (a + 1[s]);").unwrap(),
+        parse_line("d = 3[m];").unwrap(),
+    ];
+    let message = "in `c`: cannot add Length (m) to Time (s)";
+
+    let mut i: Memory = Interpreter::new(program);
+    let (line, error) = i.check_lines().unwrap_err();
+    assert_eq!((line, error.to_string().as_str()), (2, message));
+    let (line, error) = i.run_lines().unwrap_err();
+    assert_eq!((line, error.to_string().as_str()), (2, message));
+    assert_eq!(i.memory["b"].to_string(), "201.168 m");
+}
+
+#[test]
+fn test_interpreter_execute() {
+    let mut i: Memory = Interpreter::new(vec![]);
+
+    i.execute(vec![AstNode::Variable {
+        name: Box::new(AstNode::Name("a".to_string())),
+        expr: Box::new(AstNode::Double {
+            value: 2.0,
+            unit: UnitExpression::new("m", 1),
+        }),
+    }])
+    .unwrap();
+
+    let failing_line = vec![
+        AstNode::Variable {
+            name: Box::new(AstNode::Name("b".to_string())),
+            expr: Box::new(AstNode::Name("a".to_string())),
+        },
+        AstNode::Variable {
+            name: Box::new(AstNode::Name("c".to_string())),
+            expr: Box::new(AstNode::Name("missing".to_string())),
+        },
+    ];
+    assert!(i.execute(failing_line).is_err());
+
+    assert_eq!(i.variables(), vec!["a".to_string()]);
+    assert_eq!(
+        i.memory["a"],
+        Value::new(2.0, Unit::new(1.0, Dimension::length()))
+    );
+    assert!(!i.memory.contains_key("b"));
+}
+
+#[test]
+fn test_interpreter_compound_units() {
+    let i: Memory = Interpreter::new(vec![]);
+
+    let acceleration = i
+        .evaluate(AstNode::Double {
+            value: 9.81,
+            unit: UnitExpression::product(&[("m", 1), ("s", -2)]),
+        })
+        .unwrap();
+    assert_eq!(
+        acceleration.dimension,
+        Dimension::length() / Dimension::time().powi(2)
+    );
+
+    assert_eq!(
+        i.resolve_unit(&UnitExpression::product(&[("km", 1), ("h", -1)])),
+        Ok(Unit::new(
+            1000.0 / 3600.0,
+            Dimension::length() / Dimension::time()
+        ))
+    );
+    assert_eq!(
+        i.resolve_unit(&UnitExpression::product(&[("W", 1), ("m", -2), ("K", -1)]))
+            .unwrap()
+            .dimension,
+        Dimension::mass() / Dimension::time().powi(3) / Dimension::temperature()
+    );
+    assert_eq!(
+        i.resolve_unit(&UnitExpression::product(&[("m", 1), ("parsec", -1)])),
+        Err(CrunchError::UnknownUnit {
+            unit: "parsec".to_string(),
+            suggestions: vec!["arcsec".to_string()]
+        })
+    );
+}
+
+#[test]
+fn test_interpreter_units() {
+    let mut i: Memory = Interpreter::new(vec![vec![
+        AstNode::Variable {
+            name: Box::new(AstNode::Name("a".to_string())),
+            expr: Box::new(AstNode::Double {
+                value: 3.0,
+                unit: UnitExpression::new("mm", 2),
+            }),
+        },
+        AstNode::Variable {
+            name: Box::new(AstNode::Name("b".to_string())),
+            expr: Box::new(AstNode::Double {
+                value: 1.0,
+                unit: UnitExpression::new("USD", 1),
+            }),
+        },
+    ]]);
+
+    assert_eq!(
+        i.run(),
+        Err(CrunchError::Evaluation {
+            variable: "b".to_string(),
+            error: Box::new(CrunchError::UnknownUnit {
+                unit: "USD".to_string(),
+                suggestions: vec![]
+            }),
+        })
+    );
+    assert_eq!(
+        i.memory["a"],
+        Value::new(3.0, Unit::new(1e-3, Dimension::length()).powi(2))
+    );
+}
+
+#[test]
+fn test_interpreter_conversion() {
+    let mut i: Memory = Interpreter::new(vec![]);
+    i.execute(vec![AstNode::Variable {
+        name: Box::new(AstNode::Name("a".to_string())),
+        expr: Box::new(AstNode::Vector {
+            value: vec![1500.0, 250.0],
+            unit: UnitExpression::new("m", 1),
+        }),
+    }])
+    .unwrap();
+
+    let in_kilometers = AstNode::Conversion {
+        expr: Box::new(AstNode::Name("a".to_string())),
+        unit: UnitExpression::new("km", 1),
+    };
+    assert_eq!(
+        i.evaluate(in_kilometers).unwrap().to_string(),
+        "[1.5 0.25] km"
+    );
+
+    let in_seconds = AstNode::Conversion {
+        expr: Box::new(AstNode::Name("a".to_string())),
+        unit: UnitExpression::new("s", 1),
+    };
+    let error = CrunchError::Conversion {
+        from: Dimension::length(),
+        to: UnitExpression::new("s", 1),
+    };
+    assert_eq!(i.evaluate(in_seconds.clone()), Err(error.clone()));
+
+    let unchecked: Memory = Interpreter::new(vec![vec![AstNode::Variable {
+        name: Box::new(AstNode::Name("b".to_string())),
+        expr: Box::new(AstNode::Conversion {
+            expr: Box::new(AstNode::Double {
+                value: 5.0,
+                unit: UnitExpression::new("m", 1),
+            }),
+            unit: UnitExpression::new("s", 1),
+        }),
+    }]]);
+    assert_eq!(
+        unchecked.check(),
+        Err(CrunchError::Evaluation {
+            variable: "b".to_string(),
+            error: Box::new(error),
+        })
+    );
+}
+
+#[test]
+fn test_interpreter_declared_dimensions() {
+    let mut i: Memory = Interpreter::new(vec![
+        parse_line("dimension Currency; unit USD : Currency; unit EUR = 1.25[USD];").unwrap(),
+        parse_line("price = (0.25[EUR] / 1[kWh]); rent = (1200[USD] / 1[month]);").unwrap(),
+        parse_line("bill = (price * 4[MWh]) -> [EUR];").unwrap(),
+    ]);
+    let currency = i.units.lookup("USD");
+    assert_eq!(
+        currency, None,
+        "nothing is declared before the program runs"
+    );
+
+    let dimensions = i.check().unwrap();
+    assert_eq!(dimensions[1].1.to_string(), "USD/s");
+    i.run().unwrap();
+
+    let usd = i.units.lookup("USD").unwrap().dimension;
+    assert_eq!(usd.describe(), "Currency (USD)");
+    assert_eq!(dimensions[0].1, i.memory["price"].dimension);
+    assert_eq!(i.memory["price"].dimension.to_string(), "USD*s^2/(kg*m^2)");
+    assert_eq!(i.memory["bill"].to_string(), "1000 EUR");
+
+    let mut error = |line| execute_error(&mut i, line);
+    assert_eq!(
+        error("x = (1[USD] + 1[m]);"),
+        "in `x`: cannot add Currency (USD) to Length (m)"
+    );
+    assert_eq!(
+        error("dimension Currency;"),
+        "in `Currency`: dimension `Currency` is already declared"
+    );
+    assert_eq!(
+        error("unit GBP : Currency;"),
+        "in `GBP`: dimension `Currency` already has the base unit `USD`"
+    );
+    assert_eq!(
+        error("unit BTC : Crypto;"),
+        "in `BTC`: unknown dimension `Crypto`"
+    );
+    assert_eq!(
+        error("unit EUR = 1.1[USD];"),
+        "in `EUR`: unit `EUR` is already defined"
+    );
+}
+
+#[test]
+fn test_interpreter_unit_declarations() {
+    let mut i: Memory = Interpreter::new(vec![
+        parse_line("unit furlong = 201.168[m]; unit rack = 42[U]; unit sprint = 2[week];").unwrap(),
+        parse_line("prefixable unit pc = 3.0857e16[m];").unwrap(),
+        parse_line("pace = (1[furlong] / 1[sprint]) -> [furlong/wk];").unwrap(),
+        parse_line("height = 1[rack] -> [U];").unwrap(),
+    ]);
+    i.check().unwrap();
+    i.run().unwrap();
+
+    assert_eq!(i.memory["pace"].to_string(), "0.5 furlong/wk");
+    assert_eq!(i.memory["height"].to_string(), "42 U");
+    assert_eq!(
+        i.units.lookup("Mpc").map(|unit| unit.dimension),
+        Some(Dimension::length())
+    );
+    let kiloparsec = i.units.lookup("kpc").unwrap().conversion_factor;
+    assert!((kiloparsec - 3.0857e19).abs() < 1e7);
+
+    assert_eq!(
+        execute_error(&mut i, "x = 1[kfurlong];"),
+        "in `x`: unknown unit `kfurlong`, did you mean `furlong`?"
+    );
+    assert_eq!(
+        execute_error(&mut i, "unit kpc = 1[m];"),
+        "in `kpc`: unit `kpc` is already defined"
+    );
+
+    // running and checking agree that a unit cannot be defined by a variable
+    let program = || vec![parse_line("a = 2[m]; unit step = a * 1; x = 3[step];").unwrap()];
+    let message = "in `step`: unit values cannot reference variables such as `a`";
+    let checked: Memory = Interpreter::new(program());
+    assert_eq!(checked.check().unwrap_err().to_string(), message);
+    let mut ran: Memory = Interpreter::new(program());
+    assert_eq!(ran.run().unwrap_err().to_string(), message);
+
+    // a physical constant is not a variable
+    i.execute(parse_line("unit lightyear = const.c * 1[yr];").unwrap())
+        .unwrap();
+    assert_eq!(
+        i.units.lookup("lightyear").map(|unit| unit.dimension),
+        Some(Dimension::length())
+    );
+}
+
+#[test]
+fn test_interpreter_constants() {
+    let mut i: Memory = Interpreter::new(vec![
+        parse_line("c = 2[m]; weight = (100[kg] * const.g0);").unwrap(),
+        parse_line("energy = (1[g] * (const.c * const.c)) -> [kWh];").unwrap(),
+        parse_line("T = 300[K]; gas = (2[mol] * const.R * T / 1[m^3]) -> [Pa];").unwrap(),
+        parse_line("thermal = const.k_B * T; heat = 1[W/K] * 10[K];").unwrap(),
+    ]);
+    let dimensions = i.check().unwrap();
+    assert_eq!(dimensions[1].1.canonical(), "N");
+    i.run().unwrap();
+
+    // a variable called `c` does not affect the speed of light
+    assert_eq!(i.memory["c"].to_string(), "2 m");
+    assert_eq!(i.memory["weight"].to_string(), "980.665 N");
+    assert_eq!(i.memory["energy"].to_string(), "24965421.631578267 kWh");
+
+    // constants work with absolute temperatures, which are kept in kelvin
+    assert_eq!(i.memory["gas"].to_string(), "4988.677570891944 Pa");
+    assert_eq!(i.memory["thermal"].dimension.canonical(), "J");
+    assert_eq!(i.memory["heat"].to_string(), "10 W");
+
+    let mut error = |line| execute_error(&mut i, line);
+    assert_eq!(
+        error("const.c = 3e8[m/s];"),
+        "in `const.c`: cannot assign to a physical constant, use `override` to replace it"
+    );
+    assert_eq!(
+        error("override const.pi = 3[m^0];"),
+        "in `const.pi`: there is no such physical constant"
+    );
+    assert_eq!(
+        error("override speed = 3[m/s];"),
+        "in `speed`: there is no such physical constant"
+    );
+
+    i.execute(parse_line("override const.g0 = 10[m/s^2]; w = (2[kg] * const.g0);").unwrap())
+        .unwrap();
+    assert_eq!(i.memory["w"].to_string(), "20 N");
+    assert_eq!(
+        i.variables(),
+        vec!["c", "weight", "energy", "T", "gas", "thermal", "heat", "w"]
+    );
+}
+
+#[test]
+fn test_interpreter_suggestions() {
+    let mut i: Memory = Interpreter::new(vec![parse_line("speed = 3[m/s];").unwrap()]);
+    i.run().unwrap();
+    let mut error = |line| execute_error(&mut i, line);
+
+    assert_eq!(
+        error("d = 2[kilometre];"),
+        "in `d`: unknown unit `kilometre`, did you mean `kilometer`?"
+    );
+    assert_eq!(
+        error("d = 2[metres];"),
+        "in `d`: unknown unit `metres`, did you mean `meters`?"
+    );
+    assert_eq!(
+        error("p = 2[KPA];"),
+        "in `p`: unknown unit `KPA`, did you mean `kPa`?"
+    );
+    assert_eq!(
+        error("v = 2[pt];"),
+        "in `v`: unknown unit `pt`, did you mean `uspt` or `imppt`?"
+    );
+    assert_eq!(
+        error("t = (speed * 1[fortnight]);"),
+        "in `t`: unknown unit `fortnight`"
+    );
+    assert_eq!(
+        error("d = (spede * 2[s]);"),
+        "in `d`: undefined variable `spede`, did you mean `speed`?"
+    );
+    assert_eq!(
+        error("w = (2[kg] * g0);"),
+        "in `w`: undefined variable `g0`, did you mean `const.g0`?"
+    );
+
+    // checking suggests the same names without evaluating anything
+    let i: Memory = Interpreter::new(vec![
+        parse_line("speed = 3[m/s]; d = (Speed * 2[s]);").unwrap()
+    ]);
+    assert_eq!(
+        i.check().unwrap_err().to_string(),
+        "in `d`: undefined variable `Speed`, did you mean `speed`?"
+    );
+}
+
+#[test]
+fn test_interpreter_angles() {
+    let mut i: Memory = Interpreter::new(vec![
+        parse_line("slope = 30[deg]; rise = (10[m] * sin(slope));").unwrap(),
+        parse_line("heading = atan2(3[km], 3[km]) -> [deg]; quarter = 0.25[turn] -> [grad];")
+            .unwrap(),
+        parse_line("arc = drop_angle((2[m] * 90[deg])); spin = (1[turn] / 2[s]);").unwrap(),
+    ]);
+    let dimensions = i.check().unwrap();
+    assert_eq!(dimensions[1].1, Dimension::length());
+    i.run().unwrap();
+
+    assert_eq!(i.memory["slope"].dimension.describe(), "Angle (rad)");
+    assert_eq!(i.memory["rise"].to_string(), "4.999999999999999 m");
+    assert_eq!(i.memory["heading"].to_string(), "45 deg");
+    assert_eq!(i.memory["quarter"].to_string(), "99.99999999999999 grad");
+    assert_eq!(i.memory["arc"].to_string(), "3.141592653589793 m");
+    assert_eq!(i.memory["spin"].to_string(), "3.141592653589793 rad/s");
+
+    let mut error = |line| execute_error(&mut i, line);
+    assert_eq!(
+        error("x = sin(3[m]);"),
+        "in `x`: `sin` takes an angle or a dimensionless number, not Length (m)"
+    );
+    assert_eq!(
+        error("x = (1[rad] + 1[m^0]);"),
+        "in `x`: cannot add Angle (rad) to Dimensionless (1)"
+    );
+    assert_eq!(
+        error("dimension Angle;"),
+        "in `Angle`: dimension `Angle` is already declared"
+    );
+}
+
+#[test]
+fn test_interpreter_powers() {
+    let mut i: Memory = Interpreter::new(vec![
+        parse_line("side = 3[m]; area = side ^ 2; volume = side ^ (1 + 2);").unwrap(),
+        parse_line("root = 16[m^2] ^ 0.5; rate = 2[m/s] ^ -1; noise = 4[V^2/Hz] ^ (1/2);").unwrap(),
+        parse_line("n = 3; growth = 2 ^ n; squares = [1 2 3] ^ 2; bits = 2 ^ [1 2 3];").unwrap(),
+    ]);
+    let dimensions = i.check().unwrap();
+    assert_eq!(dimensions[2].1, Dimension::length().powi(3));
+    i.run().unwrap();
+
+    assert_eq!(i.memory["area"].to_string(), "9 m^2");
+    assert_eq!(i.memory["volume"].to_string(), "27 m^3");
+    assert_eq!(i.memory["root"].to_string(), "4 m");
+    assert_eq!(i.memory["rate"].to_string(), "0.5 s/m");
+    assert_eq!(
+        i.memory["noise"].dimension,
+        Dimension::mass() * Dimension::length().powi(2)
+            / Dimension::current()
+            / Dimension::time().pow(Rational::new(5, 2).unwrap())
+    );
+    assert_eq!(i.memory["growth"].to_string(), "8");
+    assert_eq!(i.memory["squares"].to_string(), "[1 4 9]");
+    assert_eq!(i.memory["bits"].to_string(), "[2 4 8]");
+
+    let mut error = |line| execute_error(&mut i, line);
+    assert_eq!(
+        error("x = side ^ 2[m];"),
+        "in `x`: the exponent must be dimensionless, not Length (m)"
+    );
+    assert_eq!(
+        error("x = side ^ n;"),
+        "in `x`: a quantity with units can only be raised to a constant exponent, not one computed from variables"
+    );
+    assert_eq!(
+        error("x = side ^ [1 2];"),
+        "in `x`: a quantity with units cannot be raised to a vector exponent"
+    );
+    assert_eq!(
+        error("x = side ^ 0.123;"),
+        "in `x`: a quantity with units can only be raised to a simple fraction, not 0.123"
+    );
+    // powers beyond what a `Rational` holds are errors rather than overflows
+    for line in [
+        "x = 1[m^2000000000] * 1[m^2000000000];",
+        "x = (1[m] ^ 2000000000) ^ 2;",
+        "x = 1[m^2000000000*m^2000000000];",
+    ] {
+        assert_eq!(error(line), "in `x`: unit power out of range");
+    }
+    // results outside the reals are errors rather than NaN or infinity
+    assert_eq!(
+        error("x = (-4[m^2]) ^ 0.5;"),
+        "in `x`: the result is not a real number"
+    );
+    assert_eq!(error("x = 0[m] ^ -1;"), "in `x`: the result is infinite");
+    assert_eq!(
+        error("x = acos(2);"),
+        "in `x`: the result is not a real number"
+    );
+    assert_eq!(
+        error("x = [4 -4] ^ 0.5;"),
+        "in `x`: the result is not a real number"
+    );
+
+    // checking finds the same errors without evaluating
+    let i: Memory = Interpreter::new(vec![parse_line("n = 2; x = 3[m] ^ n;").unwrap()]);
+    assert!(i.check().is_err());
+    let i: Memory = Interpreter::new(vec![
+        parse_line("x = 1[m^2000000000] / 1[m^-2000000000];").unwrap()
+    ]);
+    assert!(i.check().is_err());
+}
+
+#[test]
+fn test_interpreter_negation() {
+    let mut i: Memory = Interpreter::new(vec![
+        parse_line("a = 2[m]; b = 3[m]; c = -(a + b); d = -a * b; e = +a;").unwrap(),
+        parse_line("v = -[1 -2 3][m]; f = abs(v); k = -5[km] -> [km]; g = abs(k);").unwrap(),
+        parse_line("t = 20[degC]; dt = -(t - 25[degC]) -> [delta_degC];").unwrap(),
+    ]);
+    let dimensions = i.check().unwrap();
+    assert_eq!(dimensions[2].1, Dimension::length());
+    i.run().unwrap();
+
+    assert_eq!(i.memory["c"].to_string(), "-5 m");
+    assert_eq!(i.memory["d"].to_string(), "-6 m^2");
+    assert_eq!(i.memory["e"].to_string(), "2 m");
+    assert_eq!(i.memory["v"].to_string(), "[-1 2 -3] m");
+    assert_eq!(i.memory["f"].to_string(), "[1 2 3] m");
+    assert_eq!(i.memory["g"].to_string(), "5 km");
+    assert_eq!(i.memory["dt"].to_string(), "5 delta_degC");
+
+    assert_eq!(
+        execute_error(&mut i, "x = -t;"),
+        "in `x`: cannot negate an absolute temperature"
+    );
 }
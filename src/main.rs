@@ -1,37 +1,256 @@
-use std::fs::File;
-use std::io::Read;
-use std::process;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process::ExitCode;
 
+use clap::{Args, Parser, Subcommand};
+
+pub mod constants;
+pub mod error;
+pub mod functions;
+pub mod gnu_units;
 pub mod interpreter;
+pub mod output;
 pub mod parser;
+pub mod rational;
+pub mod repl;
+pub mod suggest;
 pub mod types;
+pub mod units;
 
+use error::CrunchError;
 use interpreter::{Interpreter, Memory};
+use output::OutputFormat;
 use parser::*;
+use types::AstNode;
+use units::UnitRegistry;
+
+/// The program parsed or evaluated, but reported an error.
+const EXIT_PROGRAM_ERROR: u8 = 1;
+/// An input file or standard input could not be read.
+const EXIT_INPUT_ERROR: u8 = 3;
+
+/// Unit aware arithmetic.
+#[derive(Parser)]
+#[command(name = "crunch", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    /// How to print results
+    #[arg(long, value_enum, global = true, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+
+    /// Declare the dimensions and units in a file, e.g. `unit furlong = 201.168[m];`
+    #[arg(long = "units-file", value_name = "FILE", global = true)]
+    units_files: Vec<PathBuf>,
+
+    /// Import the units of a file written for GNU units, e.g. its definitions.units
+    #[arg(long = "gnu-units-file", value_name = "FILE", global = true)]
+    gnu_units_files: Vec<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Evaluate programs and print every variable
+    Run(Source),
+    /// Parse and dimension-check programs without evaluating them
+    Check(Source),
+    /// Evaluate a single expression, e.g. `(2[km] * 3[m])`
+    Eval {
+        /// The expression to evaluate, which may start with `-`
+        #[arg(allow_hyphen_values = true)]
+        expression: String,
+    },
+    /// Start an interactive session
+    Repl,
+}
+
+#[derive(Args)]
+struct Source {
+    /// Program files; reads standard input when none are given or for `-`
+    files: Vec<PathBuf>,
 
-fn main() -> () {
-    let mut test_file = File::open("./test.cr").unwrap();
-    let mut input_file_contents = String::new();
-    test_file.read_to_string(&mut input_file_contents).unwrap();
+    /// Program text to use instead of files
+    #[arg(short = 'e', long = "expression", conflicts_with = "files")]
+    expression: Option<String>,
+}
+
+fn read_file(file: &PathBuf) -> io::Result<String> {
+    fs::read_to_string(file)
+        .map_err(|error| io::Error::new(error.kind(), format!("{}: {}", file.display(), error)))
+}
+
+/// Reads every program source as `(name, contents)`.
+fn read_sources(source: &Source) -> io::Result<Vec<(String, String)>> {
+    if let Some(expression) = &source.expression {
+        return Ok(vec![("-e".to_string(), expression.clone())]);
+    }
 
+    let stdin = PathBuf::from("-");
+    let files = match source.files.is_empty() {
+        true => vec![&stdin],
+        false => source.files.iter().collect(),
+    };
+
+    files
+        .into_iter()
+        .map(|file| {
+            let mut contents = String::new();
+            if file == &stdin {
+                io::stdin().read_to_string(&mut contents)?;
+                Ok(("<stdin>".to_string(), contents))
+            } else {
+                Ok((file.display().to_string(), read_file(file)?))
+            }
+        })
+        .collect()
+}
+
+/// Parses each source into one line of the program per statement, each with
+/// its location as `name:line`. Errors are reported as `name:line: error` with
+/// the line of the statement.
+fn parse_sources(sources: &[(String, String)]) -> Result<Vec<(String, AstNode)>, String> {
     let mut program = vec![];
-    for line in input_file_contents.lines().by_ref() {
-        if line.is_empty() {
-            continue;
+    for (name, contents) in sources {
+        let statements = parse_program(contents)
+            .map_err(|(line, error)| format!("{}:{}: {}", name, line, error))?;
+        program.extend(
+            statements
+                .into_iter()
+                .map(|located| (format!("{}:{}", name, located.line), located.statement)),
+        );
+    }
+    Ok(program)
+}
+
+fn input_error(error: io::Error) -> ExitCode {
+    eprintln!("error: {}", error);
+    ExitCode::from(EXIT_INPUT_ERROR)
+}
+
+/// The built in units, extended by every `--gnu-units-file` and then by the
+/// declarations in every `--units-file`.
+fn load_units(cli: &Cli) -> Result<UnitRegistry, ExitCode> {
+    let mut units = UnitRegistry::builtin();
+    for file in &cli.gnu_units_files {
+        let contents = read_file(file).map_err(input_error)?;
+        let skipped = gnu_units::import(&mut units, &contents).map_err(report)?;
+        if !skipped.is_empty() {
+            eprintln!(
+                "warning: {}: skipped {} definition(s) which crunch cannot use",
+                file.display(),
+                skipped.len()
+            );
         }
-        let (input, parsed_line) = parse_line(line).unwrap();
-        if !input.is_empty() {
-            eprintln!("parsing error, input remaining {:?}", input);
-            process::exit(1);
+    }
+
+    let mut declarations = Memory::with_units(vec![], units);
+    for file in &cli.units_files {
+        let contents = read_file(file).map_err(input_error)?;
+        let statements = parse_program(&contents).map_err(|(line, error)| {
+            eprintln!("error: {}:{}: {}", file.display(), line, error);
+            ExitCode::from(EXIT_PROGRAM_ERROR)
+        })?;
+        for Located {
+            line, statement, ..
+        } in statements
+        {
+            let location = format!("{}:{}", file.display(), line);
+            // variables and constant overrides would only last while loading
+            let declaration = matches!(
+                statement,
+                AstNode::DimensionDeclaration(_)
+                    | AstNode::BaseUnitDeclaration { .. }
+                    | AstNode::UnitDeclaration { .. }
+            );
+            if !declaration {
+                eprintln!(
+                    "error: {}: a units file can only declare dimensions and units",
+                    location
+                );
+                return Err(ExitCode::from(EXIT_PROGRAM_ERROR));
+            }
+            declarations.execute(vec![statement]).map_err(|error| {
+                eprintln!("error: {}: {}", location, error);
+                ExitCode::from(EXIT_PROGRAM_ERROR)
+            })?;
         }
-        program.push(parsed_line);
     }
+    Ok(declarations.units)
+}
+
+/// The program of `source`, with the location of each of its lines.
+fn load(source: &Source, units: UnitRegistry) -> Result<(Memory, Vec<String>), ExitCode> {
+    let sources = read_sources(source).map_err(input_error)?;
+    let program = parse_sources(&sources).map_err(|error| {
+        eprintln!("error: {}", error);
+        ExitCode::from(EXIT_PROGRAM_ERROR)
+    })?;
+    let (locations, statements): (Vec<String>, Vec<AstNode>) = program.into_iter().unzip();
+    let lines = statements
+        .into_iter()
+        .map(|statement| vec![statement])
+        .collect();
+    Ok((Memory::with_units(lines, units), locations))
+}
+
+fn report(error: CrunchError) -> ExitCode {
+    eprintln!("error: {}", error);
+    ExitCode::from(EXIT_PROGRAM_ERROR)
+}
 
-    let mut i: Memory = Interpreter::new(program.clone());
+/// Reports an error in the line at `index` as `name:line: error`.
+fn report_line(locations: &[String], (index, error): (usize, CrunchError)) -> ExitCode {
+    eprintln!("error: {}: {}", locations[index], error);
+    ExitCode::from(EXIT_PROGRAM_ERROR)
+}
+
+fn run(source: &Source, units: UnitRegistry, format: OutputFormat) -> Result<String, ExitCode> {
+    let (mut i, locations) = load(source, units)?;
+    i.run_lines()
+        .map_err(|error| report_line(&locations, error))?;
+    Ok(output::render_values(&i.values(), format))
+}
+
+fn check(source: &Source, units: UnitRegistry, format: OutputFormat) -> Result<String, ExitCode> {
+    let (i, locations) = load(source, units)?;
+    let dimensions = i
+        .check_lines()
+        .map_err(|error| report_line(&locations, error))?;
+    Ok(output::render_dimensions(&dimensions, format))
+}
 
-    i.run();
+fn eval(expression: &str, units: UnitRegistry, format: OutputFormat) -> Result<String, ExitCode> {
+    let expression = parse_single_expression(expression).map_err(report)?;
+    let i = Memory::with_units(vec![], units);
+    let value = i.evaluate(expression).map_err(report)?;
+    Ok(output::render_value(&value, format))
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let units = match load_units(&cli) {
+        Ok(units) => units,
+        Err(code) => return code,
+    };
 
-    println!("{:#?}", i.memory);
+    let result = match &cli.command {
+        Command::Run(source) => run(source, units, cli.output),
+        Command::Check(source) => check(source, units, cli.output),
+        Command::Eval { expression } => eval(expression, units, cli.output),
+        Command::Repl => repl::repl(units).map(|_| String::new()).map_err(|error| {
+            eprintln!("error: {}", error);
+            ExitCode::from(EXIT_INPUT_ERROR)
+        }),
+    };
 
-    ()
+    match result {
+        Ok(output) => {
+            print!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(code) => code,
+    }
 }
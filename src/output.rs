@@ -0,0 +1,122 @@
+use clap::ValueEnum;
+use serde_json::json;
+
+use super::types::*;
+
+/// How results are written to standard output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// One `name = value unit` line per variable
+    Table,
+    /// A JSON array of `{"name", "value", "unit"}` objects
+    Json,
+    /// The internal representation, as printed by `{:#?}`
+    Debug,
+}
+
+fn magnitude_json(magnitude: &Magnitude) -> serde_json::Value {
+    match magnitude {
+        Magnitude::Scalar(x) => json!(x),
+        Magnitude::Vector(xs) => json!(xs),
+    }
+}
+
+fn value_json(value: &Value) -> serde_json::Value {
+    let (magnitude, unit) = value.displayed();
+    json!({
+        "value": magnitude_json(&magnitude),
+        "unit": unit,
+    })
+}
+
+fn table<T: ToString>(rows: &[(String, T)]) -> String {
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    rows.iter()
+        .map(|(name, cell)| format!("{:<width$} = {}\n", name, cell.to_string()))
+        .collect()
+}
+
+/// Renders the variables of a program run.
+pub fn render_values(values: &[(String, Value)], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Table => table(values),
+        OutputFormat::Json => {
+            let rows: Vec<serde_json::Value> = values
+                .iter()
+                .map(|(name, value)| {
+                    let mut row = value_json(value);
+                    row["name"] = json!(name);
+                    row
+                })
+                .collect();
+            format!("{}\n", serde_json::Value::Array(rows))
+        }
+        OutputFormat::Debug => format!("{:#?}\n", values),
+    }
+}
+
+/// Renders a single evaluated expression.
+pub fn render_value(value: &Value, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Table => format!("{}\n", value),
+        OutputFormat::Json => format!("{}\n", value_json(value)),
+        OutputFormat::Debug => format!("{:#?}\n", value),
+    }
+}
+
+/// Renders the dimensions found by checking a program.
+pub fn render_dimensions(dimensions: &[(String, Dimension)], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Table => table(dimensions),
+        OutputFormat::Json => {
+            let rows: Vec<serde_json::Value> = dimensions
+                .iter()
+                .map(|(name, dimension)| {
+                    json!({
+                        "name": name,
+                        "dimension": dimension.to_string(),
+                    })
+                })
+                .collect();
+            format!("{}\n", serde_json::Value::Array(rows))
+        }
+        OutputFormat::Debug => format!("{:#?}\n", dimensions),
+    }
+}
+
+#[test]
+fn test_render_values() {
+    let meters = Unit::new(1.0, Dimension::length());
+    let values = vec![
+        ("a".to_string(), Value::new(2.0, meters.clone().powi(2))),
+        (
+            "long".to_string(),
+            Value::new_vec(vec![1.0, 2.5], meters.clone()),
+        ),
+    ];
+
+    assert_eq!(
+        render_values(&values, OutputFormat::Table),
+        "a    = 2 m^2\nlong = [1 2.5] m\n"
+    );
+    // names are padded by characters, not bytes
+    assert_eq!(
+        render_values(
+            &[
+                ("Δx".to_string(), Value::new(1.0, meters.clone())),
+                ("x".to_string(), Value::new(2.0, meters))
+            ],
+            OutputFormat::Table
+        ),
+        "Δx = 1 m\nx  = 2 m\n"
+    );
+    assert_eq!(
+        render_values(&values, OutputFormat::Json),
+        "[{\"name\":\"a\",\"unit\":\"m^2\",\"value\":2.0},\
+         {\"name\":\"long\",\"unit\":\"m\",\"value\":[1.0,2.5]}]\n"
+    );
+}
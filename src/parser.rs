@@ -1,171 +1,430 @@
 extern crate nom;
-extern crate uom;
-
-use std::collections::HashMap;
-use std::ops::{Add, Div, Mul, Sub};
 
 use nom::branch::alt;
-use nom::bytes::complete::tag;
+use nom::bytes::complete::{tag, take_while, take_while1};
 use nom::character::complete::digit1;
-use nom::character::complete::{alpha1, char, space0};
-use nom::multi::{many0, many1};
+use nom::character::complete::{char, multispace1, not_line_ending, satisfy};
+use nom::combinator::{map, not, opt, peek, recognize};
+use nom::error::ParseError;
+use nom::multi::{fold_many0, many0, many1, separated_list1};
 use nom::number::complete::double;
-use nom::sequence::{delimited, preceded, terminated};
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
 use nom::IResult;
+use unicode_xid::UnicodeXID;
 
-use uom::si::area::{square_kilometer, square_meter};
-use uom::si::f64::*;
-use uom::si::length::{kilometer, meter};
-
+use super::constants;
+use super::error::{CrunchError, POWER_OUT_OF_RANGE};
+use super::rational::Rational;
 use super::types::*;
 
-fn parse_unit(input: &str) -> IResult<&str, Unit> {
-    println!("reached parse_unit {}", input.clone());
+type ParseResult<'a, T> = IResult<&'a str, T, CrunchError>;
 
-    // TODO: none of this is very nice, differentiate unit families better
+/// `/* ... */`, which may span lines. An unterminated comment is an error
+/// rather than the start of a division.
+fn parse_block_comment(input: &str) -> ParseResult<'_, &str> {
+    let (rest, _) = tag("/*")(input)?;
+    match rest.find("*/") {
+        Some(end) => Ok((&rest[end + 2..], &input[..end + 4])),
+        None => Err(nom::Err::Failure(CrunchError::parse(
+            input,
+            "unterminated comment, expected `*/`",
+        ))),
+    }
+}
 
-    // https://docs.rs/nom/latest/nom/branch/fn.alt.html
-    println!("  parsing unit {}", input.clone());
-    let (input, _) = tag("[")(input)?;
-    // println!("  parsing unit {}", input.clone());
-    let (input, unit_alias) = alt((
-        tag("meters"),
-        tag("meter"),
-        tag("m"), // longest to shortest!!
-        tag("kilometers"),
-        tag("kilometer"),
-        tag("km"),
+/// `# ...` or `// ...` to the end of the line, or a block comment.
+fn parse_comment(input: &str) -> ParseResult<'_, &str> {
+    alt((
+        recognize(pair(alt((tag("#"), tag("//"))), not_line_ending)),
+        parse_block_comment,
+    ))(input)
+}
+
+/// Whitespace, including line breaks, and comments, which can go between any
+/// two tokens so that a statement may span lines.
+fn blank0(input: &str) -> ParseResult<'_, &str> {
+    recognize(many0(alt((multispace1, parse_comment))))(input)
+}
+
+fn blank1(input: &str) -> ParseResult<'_, &str> {
+    recognize(many1(alt((multispace1, parse_comment))))(input)
+}
+
+/// The name of a unit, e.g. `km`, `µm` or `delta_degC`.
+fn parse_unit_symbol(input: &str) -> ParseResult<'_, &str> {
+    take_while1(|c: char| c.is_alphabetic() || c == '_')(input)
+}
+
+fn parse_unit_alias(input: &str) -> ParseResult<'_, UnitExpression> {
+    let (input, unit_alias) = parse_unit_symbol(input)?;
+
+    // the interpreter looks the alias up in its unit registry
+    Ok((input, UnitExpression::new(unit_alias, 1)))
+}
+
+/// An optionally negative whole number, e.g. `-2`.
+fn parse_integer(input: &str) -> ParseResult<'_, i32> {
+    let (input, integer_string) = recognize(pair(opt(char('-')), digit1))(input)?;
+
+    // `i32::MIN` has no negation, so it is not a valid `Rational` either
+    let integer = integer_string
+        .parse::<i32>()
+        .ok()
+        .filter(|integer| *integer != i32::MIN)
+        .ok_or_else(|| nom::Err::Failure(CrunchError::parse(integer_string, POWER_OUT_OF_RANGE)))?;
+    Ok((input, integer))
+}
+
+/// A fraction such as `1/2` or `-3/2`, or a whole number.
+fn parse_fraction(input: &str) -> ParseResult<'_, Rational> {
+    let (input, numerator) = parse_integer(input)?;
+    let (input, denominator) = opt(preceded(delimited(blank0, char('/'), blank0), digit1))(input)?;
+
+    let power = match denominator {
+        None => Some(Rational::integer(numerator)),
+        Some(denominator) => denominator
+            .parse::<i32>()
+            .ok()
+            .and_then(|denominator| Rational::new(numerator, denominator)),
+    };
+    match power {
+        Some(power) => Ok((input, power)),
+        None => Err(nom::Err::Failure(CrunchError::parse(
+            denominator.unwrap_or(input),
+            "invalid unit power",
+        ))),
+    }
+}
+
+/// `^2`, `^-1` or a parenthesised fraction such as `^(1/2)`.
+fn parse_unit_power(input: &str) -> ParseResult<'_, Rational> {
+    let (input, _) = delimited(blank0, tag("^"), blank0)(input)?;
+    alt((
+        map(parse_integer, Rational::integer),
+        delimited(
+            terminated(tag("("), blank0),
+            parse_fraction,
+            preceded(blank0, tag(")")),
+        ),
+    ))(input)
+}
+
+/// A unit alias or a parenthesised unit expression, with an optional power.
+fn parse_unit_term(input: &str) -> ParseResult<'_, UnitExpression> {
+    let (input, unit) = alt((
+        parse_unit_alias,
+        delimited(
+            terminated(tag("("), blank0),
+            parse_unit_expression,
+            preceded(blank0, tag(")")),
+        ),
     ))(input)?;
-    println!("  parsed unit {}", unit_alias.clone());
-    let (input, _) = tag("^")(input)?;
+    let (rest, power) = opt(parse_unit_power)(input)?;
 
-    // TODO: add some sugar here so we can write 1[m] instead of 1[m^1]
-    // println!("  parsing unit {}", input.clone());
-    let (input, power_string) = digit1(input)?;
-    println!("  parsed power {}", power_string.clone());
+    match unit.checked_pow(power.unwrap_or(Rational::ONE)) {
+        Some(unit) => Ok((rest, unit)),
+        None => Err(nom::Err::Failure(CrunchError::parse(
+            input,
+            POWER_OUT_OF_RANGE,
+        ))),
+    }
+}
 
-    let power = power_string.parse::<i64>().unwrap();
-    // println!("  parsing unit {}", input.clone());
-    let (input, _) = tag("]")(input)?;
-    // println!("  parsing unit {}", input.clone());
-
-    // TODO: We can also have a parser for each unit
-    // TODO: turn these into quantities in the interpreter
-    let dimension = match unit_alias {
-        "meters" | "meter" | "m" => match power {
-            1 => Unit::Meter,
-            // 2 => Unit::SquareMeter,
-            _ => todo!("other dimensions in meters"),
-        },
-        "kilometers" | "kilometer" | "km" => match power {
-            1 => Unit::Kilometer,
-            //2 => Unit::SquareKilometer,
-            _ => todo!("other dimensions in kilometers"),
+/// Products and quotients of unit terms, read left to right, e.g. `kg*m/s^2`.
+fn parse_unit_expression(input: &str) -> ParseResult<'_, UnitExpression> {
+    let (input, first) = parse_unit_term(input)?;
+    fold_many0(
+        pair(
+            delimited(blank0, alt((char('*'), char('/'))), blank0),
+            parse_unit_term,
+        ),
+        move || first.clone(),
+        |unit, (operator, term)| match operator {
+            '*' => unit * term,
+            _ => unit / term,
         },
-        _ => panic!("Unsupported unit alias {}", unit_alias),
-    };
+    )(input)
+}
 
-    Ok((input, dimension))
+fn parse_unit(input: &str) -> ParseResult<'_, UnitExpression> {
+    // https://docs.rs/nom/latest/nom/branch/fn.alt.html
+    delimited(
+        terminated(tag("["), blank0),
+        parse_unit_expression,
+        preceded(blank0, tag("]")),
+    )(input)
 }
 
 /// Switch on dimensions
-fn parse_dimension(input: &str) -> IResult<&str, Unit> {
-    println!("reached parse_dimension {}", input.clone());
+fn parse_dimension(input: &str) -> ParseResult<'_, UnitExpression> {
     let (input, dimension) = parse_unit(input)?;
     // let (input, dimension) = delimited(tag("["), alt((parse_unit, parse_volume)), tag("]"))(input)?;
 
     Ok((input, dimension))
 }
 
-fn parse_number(number: &str) -> IResult<&str, AstNode> {
-    println!("reached parse_number {}", number.clone());
-    let (input, number) = double(number)?;
+/// A decimal number such as `-2.5e3`. `double` on its own would also read
+/// `nan` and `inf`, and with them names such as `nanometers`.
+pub fn parse_decimal<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, f64, E> {
+    peek(pair(
+        opt(alt((char('-'), char('+')))),
+        satisfy(|c| c.is_ascii_digit() || c == '.'),
+    ))(input)?;
+    double(input)
+}
+
+/// A number with a unit, `2[km]`, or without one for a plain number, `2`.
+fn parse_number(number: &str) -> ParseResult<'_, AstNode> {
+    let sign = opt(alt((char('-'), char('+'))))(number)?.1;
+    let (input, number) = parse_decimal(number)?;
 
-    let (input, unit) = parse_dimension(input)?;
+    let (input, unit) = opt(parse_dimension)(input)?;
+    let unit = unit.unwrap_or_else(|| UnitExpression::product(&[]));
+
+    // `-2 ^ 2` is `-(2 ^ 2)`, so a signed base is left to `parse_unary`
+    if sign.is_some() {
+        not(pair(blank0, char('^')))(input)?;
+    }
 
     Ok((
         input,
         AstNode::Double {
-            value: number as f64,
-            unit: unit,
+            value: number,
+            unit,
         },
     ))
 }
 
-fn parse_vector(input: &str) -> IResult<&str, AstNode> {
-    println!("reached parse_vector {}", input.clone());
-
-    println!("  reached vector bracket open {}", input.clone());
+/// A vector with a unit, `[1 2 3][km]`, or without one, `[1 2 3]`.
+fn parse_vector(input: &str) -> ParseResult<'_, AstNode> {
     let (input, _) = tag("[")(input)?;
-    println!("  reached vector {}", input.clone());
-    let (input, vector) = many1(delimited(space0, double, space0))(input)?;
-    println!("  parsed vector {:#?}", vector.clone());
-    println!("  reached vector bracket close {}", input.clone());
+    let (input, vector) = many1(delimited(blank0, parse_decimal, blank0))(input)?;
     let (input, _) = tag("]")(input)?;
 
-    let (input, unit) = parse_dimension(input)?;
+    let (input, unit) = opt(parse_dimension)(input)?;
+    let unit = unit.unwrap_or_else(|| UnitExpression::product(&[]));
 
     Ok((
         input,
         AstNode::Vector {
             value: vector,
-            unit: unit,
+            unit,
         },
     ))
 }
 
-fn parse_value(input: &str) -> IResult<&str, AstNode> {
-    println!("reached parse_value {}", input.clone());
+fn parse_value(input: &str) -> ParseResult<'_, AstNode> {
     alt((parse_vector, parse_number))(input)
 }
 
-fn parse_name(name: &str) -> IResult<&str, AstNode> {
-    println!("reached parse_name {}", name.clone());
-    let (input, name) = alpha1(name)?;
+/// Words of the language, which cannot be used as names. `let` and `print` are
+/// kept for statements to come.
+const KEYWORDS: [&str; 8] = [
+    "const",
+    "dimension",
+    "in",
+    "let",
+    "override",
+    "prefixable",
+    "print",
+    "unit",
+];
 
-    Ok((input, AstNode::Name(name.to_string())))
+/// A word such as `v0`, `t_final` or `Δt`, following Unicode's identifier
+/// syntax: a letter, then letters, digits and underscores.
+fn parse_word(input: &str) -> ParseResult<'_, &str> {
+    recognize(pair(
+        satisfy(UnicodeXID::is_xid_start),
+        take_while(UnicodeXID::is_xid_continue),
+    ))(input)
 }
 
-fn parse_operator(input: &str) -> IResult<&str, &str> {
-    println!("reached parse_operator {}", input.clone());
-    Ok(alt((
-        terminated(preceded(space0, tag("+")), space0),
-        terminated(preceded(space0, tag("-")), space0),
-        terminated(preceded(space0, tag("*")), space0),
-        terminated(preceded(space0, tag("/")), space0),
-        terminated(preceded(space0, tag("^")), space0),
-    ))(input)?)
+/// Fails if `name`, read from the start of `input`, is a keyword.
+fn reject_keyword(input: &str, name: &str) -> Result<(), nom::Err<CrunchError>> {
+    match KEYWORDS.contains(&name) {
+        true => Err(nom::Err::Failure(CrunchError::parse(
+            input,
+            &format!("`{}` is a keyword and cannot be used as a name", name),
+        ))),
+        false => Ok(()),
+    }
 }
 
-fn parse_expression(input: &str) -> IResult<&str, AstNode> {
-    println!("reached parse_expression {}", input.clone());
+/// A variable such as `speed` or `ρ_water`, or a constant such as `const.k_B`.
+/// A keyword is an error rather than a name.
+fn parse_identifier(input: &str) -> ParseResult<'_, &str> {
+    let (rest, identifier) = alt((
+        recognize(tuple((tag(constants::NAMESPACE), char('.'), parse_word))),
+        parse_word,
+    ))(input)?;
+
+    reject_keyword(input, identifier)?;
+    Ok((rest, identifier))
+}
+
+/// The name of a declared dimension, e.g. `Currency` or `Data_rate`, which
+/// follows the same rules as a variable.
+fn parse_dimension_name(input: &str) -> ParseResult<'_, &str> {
+    let (rest, name) = parse_word(input)?;
+
+    reject_keyword(input, name)?;
+    Ok((rest, name))
+}
+
+fn parse_name(name: &str) -> ParseResult<'_, AstNode> {
+    let (input, name) = parse_identifier(name)?;
+
+    Ok((input, AstNode::Name(name.to_string())))
+}
+
+/// A function call, e.g. `sin(30[deg])` or `atan2(y, x)`.
+fn parse_call(input: &str) -> ParseResult<'_, AstNode> {
+    let (input, function) = parse_identifier(input)?;
+    let (input, arguments) = delimited(
+        pair(char('('), blank0),
+        separated_list1(delimited(blank0, char(','), blank0), parse_expression),
+        pair(blank0, char(')')),
+    )(input)?;
 
-    let (input, _) = tag("(")(input)?;
-    let (input, lhs) = alt((parse_value, parse_name, parse_expression))(input)?;
-    let (input, operator) = parse_operator(input)?;
-    let (input, rhs) = alt((parse_expression, parse_name, parse_value))(input)?;
-    let (input, _) = tag(")")(input)?;
     Ok((
         input,
-        AstNode::Expression {
+        AstNode::Call {
+            function: function.to_string(),
+            arguments,
+        },
+    ))
+}
+
+/// An expression in parentheses, which only groups: `(a + b)` gives the same
+/// tree as `a + b`.
+fn parse_group(input: &str) -> ParseResult<'_, AstNode> {
+    delimited(
+        pair(char('('), blank0),
+        parse_expression,
+        pair(blank0, char(')')),
+    )(input)
+}
+
+fn parse_operand(input: &str) -> ParseResult<'_, AstNode> {
+    alt((parse_value, parse_call, parse_name, parse_group))(input)
+}
+
+/// Binds tighter than `*` and `/` but looser than `^`, so `-a^2` is `-(a^2)`.
+/// A signed number such as `-2[m]` stays a single literal.
+fn parse_unary(input: &str) -> ParseResult<'_, AstNode> {
+    let sign = terminated(
+        alt((
+            map(char('-'), |_| UnaryOperation::Minus),
+            map(char('+'), |_| UnaryOperation::Plus),
+        )),
+        blank0,
+    );
+    let signed = pair(sign, |input| parse_binary(input, POWER_PRECEDENCE));
+
+    alt((
+        parse_operand,
+        map(signed, |(operation, operand)| AstNode::Unary {
+            operation,
+            expr: Box::new(operand),
+        }),
+    ))(input)
+}
+
+fn parse_operator(input: &str) -> ParseResult<'_, &str> {
+    delimited(
+        blank0,
+        alt((
+            tag("+"),
+            // not the start of a conversion, `-> [km]`
+            terminated(tag("-"), not(char('>'))),
+            tag("*"),
+            tag("/"),
+            tag("^"),
+        )),
+        blank0,
+    )(input)
+}
+
+const POWER_PRECEDENCE: u8 = 3;
+
+/// The precedence of a binary operator and whether it is right associative.
+fn precedence(operator: &str) -> (u8, bool) {
+    match operator {
+        "+" | "-" => (1, false),
+        "*" | "/" => (2, false),
+        _ => (POWER_PRECEDENCE, true),
+    }
+}
+
+/// Precedence climbing: parses operands joined by operators which bind at
+/// least as tightly as `min_precedence`.
+fn parse_binary(input: &str, min_precedence: u8) -> ParseResult<'_, AstNode> {
+    let (mut input, mut lhs) = parse_unary(input)?;
+
+    while let Ok((rest, operator)) = parse_operator(input) {
+        let (precedence, right_associative) = precedence(operator);
+        if precedence < min_precedence {
+            break;
+        }
+        let operation = match operator {
+            "+" => BinaryOperation::Add,
+            "-" => BinaryOperation::Subtract,
+            "*" => BinaryOperation::Multiply,
+            "/" => BinaryOperation::Divide,
+            _ => BinaryOperation::Power,
+        };
+        let next_precedence = match right_associative {
+            true => precedence,
+            false => precedence + 1,
+        };
+        let (rest, rhs) = parse_binary(rest, next_precedence)?;
+
+        lhs = AstNode::Expression {
+            operation,
             lhs: Box::new(lhs),
             rhs: Box::new(rhs),
-            operation: match operator {
-                "+" => BinaryOperation::Add,
-                "-" => BinaryOperation::Subtract,
-                "*" => BinaryOperation::Multiply,
-                "/" => BinaryOperation::Divide,
-                _ => panic!("Unsupported binary operation {}", operator),
-            },
+        };
+        input = rest;
+    }
+    Ok((input, lhs))
+}
+
+/// An expression with the usual precedence: `^` binds tightest and is right
+/// associative, then unary minus, then `*` and `/`, then `+` and `-`.
+fn parse_expression(input: &str) -> ParseResult<'_, AstNode> {
+    parse_binary(input, 0)
+}
+
+/// `-> [unit]` or `in [unit]`.
+fn parse_conversion_target(input: &str) -> ParseResult<'_, UnitExpression> {
+    preceded(
+        alt((
+            delimited(blank0, tag("->"), blank0),
+            delimited(blank1, tag("in"), blank0),
+        )),
+        parse_unit,
+    )(input)
+}
+
+/// An expression, optionally converted for display, e.g. `a * b -> [km^2]`.
+fn parse_converted(input: &str) -> ParseResult<'_, AstNode> {
+    let (input, expr) = parse_expression(input)?;
+    let (input, unit) = opt(parse_conversion_target)(input)?;
+
+    let node = match unit {
+        Some(unit) => AstNode::Conversion {
+            expr: Box::new(expr),
+            unit,
         },
-    ))
+        None => expr,
+    };
+    Ok((input, node))
 }
 
-fn parse_variable(input: &str) -> IResult<&str, AstNode> {
-    println!("reached parse_variable {}", input.clone());
+fn parse_variable(input: &str) -> ParseResult<'_, AstNode> {
     let (input, name) = parse_name(input)?;
-    let (input, _) = tag(" = ")(input)?;
-    let (input, expr) = terminated(alt((parse_value, parse_expression)), char(';'))(input)?;
+    let (input, _) = delimited(blank0, char('='), blank0)(input)?;
+    let (input, expr) = terminated(parse_converted, pair(blank0, char(';')))(input)?;
 
     Ok((
         input,
@@ -176,9 +435,195 @@ fn parse_variable(input: &str) -> IResult<&str, AstNode> {
     ))
 }
 
-pub fn parse_line(input: &str) -> IResult<&str, Vec<AstNode>> {
-    println!("reached parse_line {}", input.clone());
-    many0(preceded(space0, parse_variable))(input)
+/// `dimension Currency;`
+fn parse_dimension_declaration(input: &str) -> ParseResult<'_, AstNode> {
+    let (input, name) = delimited(
+        pair(tag("dimension"), blank1),
+        parse_dimension_name,
+        pair(blank0, char(';')),
+    )(input)?;
+
+    Ok((input, AstNode::DimensionDeclaration(name.to_string())))
+}
+
+/// The name a `unit` declaration defines, e.g. `furlong` or `delta_degR`. Like
+/// other names it starts with a letter and cannot be a keyword.
+fn parse_unit_name(input: &str) -> ParseResult<'_, &str> {
+    let (rest, name) = parse_unit_symbol(input)?;
+
+    if !name.starts_with(char::is_alphabetic) {
+        return Err(nom::Err::Failure(CrunchError::parse(
+            input,
+            "a unit name must start with a letter",
+        )));
+    }
+    reject_keyword(input, name)?;
+    Ok((rest, name))
+}
+
+/// The value a unit is defined by, which cannot be a variable.
+fn parse_unit_value(input: &str) -> ParseResult<'_, AstNode> {
+    let (rest, value) = parse_expression(input)?;
+
+    match &value {
+        AstNode::Name(name) if !constants::is_qualified(name) => Err(nom::Err::Failure(
+            CrunchError::parse(input, "unit values cannot reference variables"),
+        )),
+        _ => Ok((rest, value)),
+    }
+}
+
+/// `unit USD : Currency;` or `unit EUR = 1.08[USD];`, either of which can be
+/// marked `prefixable` so that e.g. `kEUR` is understood.
+fn parse_unit_declaration(input: &str) -> ParseResult<'_, AstNode> {
+    let (input, prefixable) = opt(pair(tag("prefixable"), blank1))(input)?;
+    let prefixable = prefixable.is_some();
+    let (input, name) = preceded(pair(tag("unit"), blank1), parse_unit_name)(input)?;
+    let name = name.to_string();
+    let (input, declaration) = terminated(
+        alt((
+            map(
+                preceded(delimited(blank0, char(':'), blank0), parse_dimension_name),
+                |dimension: &str| AstNode::BaseUnitDeclaration {
+                    name: name.clone(),
+                    dimension: dimension.to_string(),
+                    prefixable,
+                },
+            ),
+            map(
+                preceded(delimited(blank0, char('='), blank0), parse_unit_value),
+                |value| AstNode::UnitDeclaration {
+                    name: name.clone(),
+                    value: Box::new(value),
+                    prefixable,
+                },
+            ),
+        )),
+        pair(blank0, char(';')),
+    )(input)?;
+
+    Ok((input, declaration))
+}
+
+/// `override const.c = 3e8[m/s];`
+fn parse_constant_override(input: &str) -> ParseResult<'_, AstNode> {
+    let (input, name) = preceded(pair(tag("override"), blank1), parse_identifier)(input)?;
+    let (input, _) = delimited(blank0, char('='), blank0)(input)?;
+    let (input, expr) = terminated(parse_converted, pair(blank0, char(';')))(input)?;
+
+    Ok((
+        input,
+        AstNode::ConstantOverride {
+            name: name.to_string(),
+            expr: Box::new(expr),
+        },
+    ))
+}
+
+/// An assignment or a declaration.
+fn parse_statement(input: &str) -> ParseResult<'_, AstNode> {
+    alt((
+        parse_dimension_declaration,
+        parse_unit_declaration,
+        parse_constant_override,
+        parse_variable,
+    ))(input)
+}
+
+fn into_error(input: &str, error: nom::Err<CrunchError>) -> CrunchError {
+    match error {
+        nom::Err::Error(error) | nom::Err::Failure(error) => error,
+        nom::Err::Incomplete(_) => CrunchError::parse(input, "incomplete input"),
+    }
+}
+
+/// Runs `parser` over all of `input`, reporting `expected` if any is left over.
+fn parse_all<'a, T>(
+    input: &'a str,
+    mut parser: impl FnMut(&'a str) -> ParseResult<'a, T>,
+    expected: &str,
+) -> Result<T, CrunchError> {
+    let (remaining, parsed) = parser(input).map_err(|error| into_error(input, error))?;
+
+    if !remaining.trim().is_empty() {
+        return Err(CrunchError::parse(remaining.trim(), expected));
+    }
+
+    Ok(parsed)
+}
+
+/// A statement of a program with its text and the line it starts on, counting
+/// from 1.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Located<'a> {
+    pub line: usize,
+    pub text: &'a str,
+    pub statement: AstNode,
+}
+
+/// Parses a whole program. Statements end at `;` rather than at a line break,
+/// so one can span lines, and comments can go wherever whitespace can. An
+/// error comes with the line it was found on.
+pub fn parse_program(input: &str) -> Result<Vec<Located<'_>>, (usize, CrunchError)> {
+    let line = |rest: &str| input[..input.len() - rest.len()].matches('\n').count() + 1;
+    // the input of a parse error is the rest of the program where it stopped
+    let located = |rest: &str, error: CrunchError| match &error {
+        CrunchError::Parse { input: at, .. } if input.ends_with(at.as_str()) => {
+            (line(&input[input.len() - at.len()..]), error)
+        }
+        _ => (line(rest), error),
+    };
+
+    let mut statements = vec![];
+    let mut rest = input;
+    loop {
+        (rest, _) = blank0(rest).map_err(|error| located(rest, into_error(rest, error)))?;
+        if rest.is_empty() {
+            return Ok(statements);
+        }
+        match parse_statement(rest) {
+            Ok((remaining, statement)) => {
+                statements.push(Located {
+                    line: line(rest),
+                    text: &rest[..rest.len() - remaining.len()],
+                    statement,
+                });
+                rest = remaining;
+            }
+            Err(nom::Err::Error(_)) => {
+                let error = CrunchError::parse(rest, "expected `name = expression;`");
+                return Err(located(rest, error));
+            }
+            Err(error) => return Err(located(rest, into_error(rest, error))),
+        }
+    }
+}
+
+/// Parses the statements of a single line, or of any text, without locations.
+pub fn parse_line(input: &str) -> Result<Vec<AstNode>, CrunchError> {
+    let statements = parse_program(input).map_err(|(_, error)| error)?;
+    Ok(statements
+        .into_iter()
+        .map(|located| located.statement)
+        .collect())
+}
+
+/// Parses a single expression such as `(2[km] * 3[m])`, without assignment.
+pub fn parse_single_expression(input: &str) -> Result<AstNode, CrunchError> {
+    parse_all(
+        input,
+        delimited(blank0, parse_converted, blank0),
+        "expected end of expression",
+    )
+}
+
+/// Parses the inside of a unit annotation, e.g. `kg*m/s^2`.
+pub fn parse_unit_annotation(input: &str) -> Result<UnitExpression, CrunchError> {
+    parse_all(
+        input,
+        preceded(blank0, parse_unit_expression),
+        "expected a unit such as `kg*m/s^2`",
+    )
 }
 
 #[test]
@@ -188,14 +633,8 @@ fn test_parse_number() {
         Ok((
             "",
             AstNode::Double {
-                value: Value::Float(1.1),
-                dimension: Unit {
-                    unit: Unit {
-                        unit: UnitIdentity::Meter,
-                        conversion_factor: 1.0
-                    },
-                    power: 1
-                }
+                value: 1.1,
+                unit: UnitExpression::new("m", 1),
             }
         ))
     );
@@ -204,14 +643,8 @@ fn test_parse_number() {
         Ok((
             "",
             AstNode::Double {
-                value: Value::Float(1.0),
-                dimension: Unit {
-                    unit: Unit {
-                        unit: UnitIdentity::Meter,
-                        conversion_factor: 1.0
-                    },
-                    power: 1
-                },
+                value: 1.0,
+                unit: UnitExpression::new("meter", 1),
             }
         ))
     );
@@ -220,12 +653,8 @@ fn test_parse_number() {
         Ok((
             "",
             AstNode::Double {
-                value: Value::Float(1.1),
-                dimension: Unit {
-                    unit: UnitIdentity::Meter,
-                    conversion_factor: 1.0
-                },
-                power: 1
+                value: 1.1,
+                unit: UnitExpression::new("km", 1)
             }
         ))
     );
@@ -234,12 +663,8 @@ fn test_parse_number() {
         Ok((
             "",
             AstNode::Double {
-                value: Value::Float(9999999.987654),
-                dimension: Unit {
-                    unit: UnitIdentity::Meter,
-                    conversion_factor: 1.0
-                },
-                power: 1
+                value: 9999999.987654,
+                unit: UnitExpression::new("m", 1)
             }
         ))
     );
@@ -265,8 +690,8 @@ fn test_parse_variable() {
             AstNode::Variable {
                 name: Box::new(AstNode::Name("test".to_string())),
                 expr: Box::new(AstNode::Double {
-                    value: Value::Float(1.2),
-                    dimension: Unit { unit: Unit::Meter }
+                    value: 1.2,
+                    unit: UnitExpression::new("m", 1)
                 })
             }
         ))
@@ -279,14 +704,18 @@ fn test_parse_variable() {
             AstNode::Variable {
                 name: Box::new(AstNode::Name("var".to_string())),
                 expr: Box::new(AstNode::Double {
-                    value: Value::Float(-2.0),
-                    dimension: Unit {
-                        unit: Unit::Kilometer
-                    }
+                    value: -2.0,
+                    unit: UnitExpression::new("kilometers", 1)
                 })
             }
         ))
     );
+    // the spaces around `=` are optional
+    assert_eq!(
+        parse_line("a=1;override const.c=1[m/s];"),
+        parse_line("a = 1; override const.c = 1[m/s];")
+    );
+    assert_eq!(parse_line("a=1;").map(|statements| statements.len()), Ok(1));
 }
 
 #[test]
@@ -298,14 +727,12 @@ fn test_parse_expression() {
             AstNode::Expression {
                 operation: BinaryOperation::Divide,
                 lhs: Box::new(AstNode::Double {
-                    value: Value::Float(2.0),
-                    dimension: Unit {
-                        unit: Unit::Kilometer
-                    }
+                    value: 2.0,
+                    unit: UnitExpression::new("km", 1)
                 }),
                 rhs: Box::new(AstNode::Double {
-                    value: Value::Float(2.0),
-                    dimension: Unit { unit: Unit::Meter }
+                    value: 2.0,
+                    unit: UnitExpression::new("m", 1)
                 })
             }
         ))
@@ -320,27 +747,23 @@ fn test_parse_expression() {
                 lhs: Box::new(AstNode::Expression {
                     operation: BinaryOperation::Divide,
                     lhs: Box::new(AstNode::Double {
-                        value: Value::Float(2.0),
-                        dimension: Unit { unit: Unit::Meter }
+                        value: 2.0,
+                        unit: UnitExpression::new("m", 1)
                     }),
                     rhs: Box::new(AstNode::Double {
-                        value: Value::Float(2.0),
-                        dimension: Unit {
-                            unit: Unit::Kilometer
-                        }
+                        value: 2.0,
+                        unit: UnitExpression::new("km", 1)
                     })
                 }),
                 rhs: Box::new(AstNode::Expression {
                     operation: BinaryOperation::Multiply,
                     lhs: Box::new(AstNode::Double {
-                        value: Value::Float(4.0),
-                        dimension: Unit {
-                            unit: Unit::Kilometer
-                        }
+                        value: 4.0,
+                        unit: UnitExpression::new("km", 1)
                     }),
                     rhs: Box::new(AstNode::Double {
-                        value: Value::Float(4.0),
-                        dimension: Unit { unit: Unit::Meter }
+                        value: 4.0,
+                        unit: UnitExpression::new("m", 1)
                     })
                 })
             }
@@ -348,6 +771,74 @@ fn test_parse_expression() {
     );
 }
 
+#[test]
+fn test_parse_precedence() {
+    let same = |input: &str, parenthesised: &str| {
+        assert_eq!(
+            parse_single_expression(input),
+            parse_single_expression(parenthesised),
+            "{} should parse as {}",
+            input,
+            parenthesised
+        );
+    };
+
+    same("2[m] * 3[m] + 1[m^2]", "((2[m] * 3[m]) + 1[m^2])");
+    same("1[m^2] + 2[m]*3[m]", "(1[m^2] + (2[m] * 3[m]))");
+    same("a - b - c", "((a - b) - c)");
+    same("a / b * c", "((a / b) * c)");
+    same("a + b / c - d", "((a + (b / c)) - d)");
+    same("(a + b) * c", "((a + b) * c)");
+    same("((a))", "a");
+    same("sin(a + b) * c", "(sin((a + b)) * c)");
+    same("a - b -> [km]", "(a - b) -> [km]");
+    same("-a * b", "(-a * b)");
+    same("a ^ 2 ^ 3", "(a ^ (2 ^ 3))");
+    same("2 * a ^ 2", "(2 * (a ^ 2))");
+    same("-a ^ 2", "-(a ^ 2)");
+    same("-2 ^ 2", "-(2 ^ 2)");
+    same("-2[m] ^ 2", "-(2[m] ^ 2)");
+    same("2 ^ -1 ^ 2", "2 ^ (-(1 ^ 2))");
+    same("a ^ -1", "(a ^ -1)");
+    same("-(a + b) * c", "((-(a + b)) * c)");
+    same("a - -b", "(a - (-b))");
+    same("- - a", "-(-a)");
+
+    let a = AstNode::Name("a".to_string());
+    assert_eq!(
+        parse_single_expression("-a"),
+        Ok(AstNode::Unary {
+            operation: UnaryOperation::Minus,
+            expr: Box::new(a.clone())
+        })
+    );
+    assert_eq!(
+        parse_single_expression("+a"),
+        Ok(AstNode::Unary {
+            operation: UnaryOperation::Plus,
+            expr: Box::new(a)
+        })
+    );
+    // a negative number is still a single literal
+    assert_eq!(
+        parse_single_expression("-2[m] - -3[m]"),
+        parse_single_expression("(-2[m] - -3[m])")
+    );
+    assert_eq!(
+        parse_single_expression("2"),
+        Ok(AstNode::Double {
+            value: 2.0,
+            unit: UnitExpression::product(&[])
+        })
+    );
+    assert_eq!(
+        parse_single_expression("nanometers"),
+        Ok(AstNode::Name("nanometers".to_string()))
+    );
+    assert!(parse_single_expression("a + ").is_err());
+    assert!(parse_single_expression("(a + b").is_err());
+}
+
 #[test]
 fn parse_variable_expression() {
     assert_eq!(
@@ -359,14 +850,12 @@ fn parse_variable_expression() {
                 expr: Box::new(AstNode::Expression {
                     operation: BinaryOperation::Divide,
                     lhs: Box::new(AstNode::Double {
-                        value: Value::Float(2.0),
-                        dimension: Unit { unit: Unit::Meter }
+                        value: 2.0,
+                        unit: UnitExpression::new("m", 1)
                     }),
                     rhs: Box::new(AstNode::Double {
-                        value: Value::Float(2.0),
-                        dimension: Unit {
-                            unit: Unit::Kilometer
-                        }
+                        value: 2.0,
+                        unit: UnitExpression::new("km", 1)
                     })
                 })
             }
@@ -384,27 +873,23 @@ fn parse_variable_expression() {
                     lhs: Box::new(AstNode::Expression {
                         operation: BinaryOperation::Multiply,
                         lhs: Box::new(AstNode::Double {
-                            value: Value::Float(2.0),
-                            dimension: Unit { unit: Unit::Meter }
+                            value: 2.0,
+                            unit: UnitExpression::new("m", 1)
                         }),
                         rhs: Box::new(AstNode::Double {
-                            value: Value::Float(3.0),
-                            dimension: Unit {
-                                unit: Unit::Kilometer
-                            }
+                            value: 3.0,
+                            unit: UnitExpression::new("kilometers", 1)
                         }),
                     }),
                     rhs: Box::new(AstNode::Expression {
                         operation: BinaryOperation::Add,
                         lhs: Box::new(AstNode::Double {
-                            value: Value::Float(4.0),
-                            dimension: Unit { unit: Unit::Meter }
+                            value: 4.0,
+                            unit: UnitExpression::new("meters", 1)
                         }),
                         rhs: Box::new(AstNode::Double {
-                            value: Value::Float(5.0),
-                            dimension: Unit {
-                                unit: Unit::Kilometer
-                            }
+                            value: 5.0,
+                            unit: UnitExpression::new("km", 1)
                         }),
                     })
                 })
@@ -417,43 +902,493 @@ fn parse_variable_expression() {
 fn parse_variables_and_abstract_expressions() {
     assert_eq!(
         parse_line("x = (2[m] * 2[kilometer]); y = 1[km]; z = (x + y);"),
+        Ok(vec![
+            AstNode::Variable {
+                name: Box::new(AstNode::Name("x".to_string())),
+                expr: Box::new(AstNode::Expression {
+                    operation: BinaryOperation::Multiply,
+                    lhs: Box::new(AstNode::Double {
+                        value: 2.0,
+                        unit: UnitExpression::new("m", 1)
+                    }),
+                    rhs: Box::new(AstNode::Double {
+                        value: 2.0,
+                        unit: UnitExpression::new("kilometer", 1)
+                    })
+                })
+            },
+            AstNode::Variable {
+                name: Box::new(AstNode::Name("y".to_string())),
+                expr: Box::new(AstNode::Double {
+                    value: 1.0,
+                    unit: UnitExpression::new("km", 1)
+                })
+            },
+            AstNode::Variable {
+                name: Box::new(AstNode::Name("z".to_string())),
+                expr: Box::new(AstNode::Expression {
+                    operation: BinaryOperation::Add,
+                    lhs: Box::new(AstNode::Name("x".to_string())),
+                    rhs: Box::new(AstNode::Name("y".to_string()))
+                })
+            },
+        ])
+    );
+}
+
+#[test]
+fn test_parse_single_expression() {
+    assert_eq!(
+        parse_single_expression(" (a * 2[km^1]) "),
+        Ok(AstNode::Expression {
+            operation: BinaryOperation::Multiply,
+            lhs: Box::new(AstNode::Name("a".to_string())),
+            rhs: Box::new(AstNode::Double {
+                value: 2.0,
+                unit: UnitExpression::new("km", 1)
+            })
+        })
+    );
+    assert!(parse_single_expression("a = 1[m^1];").is_err());
+}
+
+#[test]
+fn test_parse_errors() {
+    assert!(matches!(
+        parse_line("a = (1[m^1] ^);"),
+        Err(CrunchError::Parse { .. })
+    ));
+    assert!(matches!(
+        parse_line("a = 1[m^1]; b ="),
+        Err(CrunchError::Parse { .. })
+    ));
+}
+
+#[test]
+fn test_parse_compound_unit() {
+    assert_eq!(
+        parse_unit("[kg*m/s^2]"),
+        Ok((
+            "",
+            UnitExpression::product(&[("kg", 1), ("m", 1), ("s", -2)])
+        ))
+    );
+    assert_eq!(
+        parse_unit("[m/s]"),
+        Ok(("", UnitExpression::product(&[("m", 1), ("s", -1)])))
+    );
+    assert_eq!(
+        parse_unit("[W/(m^2*K)]"),
+        Ok((
+            "",
+            UnitExpression::product(&[("W", 1), ("m", -2), ("K", -1)])
+        ))
+    );
+    assert_eq!(
+        parse_unit("[ (m / s)^2 * kg ]"),
+        Ok((
+            "",
+            UnitExpression::product(&[("m", 2), ("s", -2), ("kg", 1)])
+        ))
+    );
+    // quotients are read left to right
+    assert_eq!(
+        parse_unit("[J/kg/K]"),
+        Ok((
+            "",
+            UnitExpression::product(&[("J", 1), ("kg", -1), ("K", -1)])
+        ))
+    );
+    assert_eq!(
+        parse_number("9.81[m/s^2]"),
+        Ok((
+            "",
+            AstNode::Double {
+                value: 9.81,
+                unit: UnitExpression::product(&[("m", 1), ("s", -2)])
+            }
+        ))
+    );
+    assert_eq!(
+        parse_unit_annotation("kg*m/s^2"),
+        Ok(UnitExpression::product(&[("kg", 1), ("m", 1), ("s", -2)]))
+    );
+    assert!(parse_unit("[m*]").is_err());
+}
+
+#[test]
+fn test_parse_unit() {
+    assert_eq!(parse_unit("[µm^2]"), Ok(("", UnitExpression::new("µm", 2))));
+    assert_eq!(
+        parse_unit("[kilometers^1]"),
+        Ok(("", UnitExpression::new("kilometers", 1)))
+    );
+}
+
+#[test]
+fn test_parse_implicit_power() {
+    assert_eq!(parse_unit("[ km ]"), parse_unit("[km^1]"));
+    assert_eq!(
+        parse_vector("[1 2 3][km]"),
         Ok((
             "",
-            vec![
-                AstNode::Variable {
-                    name: Box::new(AstNode::Name("x".to_string())),
-                    expr: Box::new(AstNode::Expression {
+            AstNode::Vector {
+                value: vec![1.0, 2.0, 3.0],
+                unit: UnitExpression::new("km", 1)
+            }
+        ))
+    );
+    // like a number, a vector without a unit is dimensionless
+    assert_eq!(
+        parse_vector("[1 2]"),
+        Ok((
+            "",
+            AstNode::Vector {
+                value: vec![1.0, 2.0],
+                unit: UnitExpression::product(&[])
+            }
+        ))
+    );
+    assert_eq!(
+        parse_line("a = [1 2][m]; b = (a * 2[km]);").map(|statements| statements.len()),
+        Ok(2)
+    );
+}
+
+#[test]
+fn test_parse_unit_powers() {
+    let half = Rational::new(1, 2).unwrap();
+
+    assert_eq!(parse_unit("[s^-1]"), Ok(("", UnitExpression::new("s", -1))));
+    assert_eq!(
+        parse_unit("[m^(1/2)]"),
+        Ok(("", UnitExpression::new("m", 1).pow(half)))
+    );
+    assert_eq!(
+        parse_unit("[V/Hz^( 1 / 2 )]"),
+        Ok((
+            "",
+            UnitExpression::new("V", 1) * UnitExpression::new("Hz", 1).pow(-half)
+        ))
+    );
+    assert_eq!(
+        parse_unit("[Hz^(-1/2)]"),
+        Ok(("", UnitExpression::new("Hz", 1).pow(-half)))
+    );
+    assert_eq!(
+        parse_unit("[(m^2)^(3/4)]"),
+        Ok((
+            "",
+            UnitExpression::new("m", 1).pow(Rational::new(3, 2).unwrap())
+        ))
+    );
+    assert_eq!(
+        parse_unit("[kg*m^-1*s^-2]"),
+        Ok((
+            "",
+            UnitExpression::product(&[("kg", 1), ("m", -1), ("s", -2)])
+        ))
+    );
+    assert_eq!(
+        parse_unit("[m^(1/0)]"),
+        Err(nom::Err::Failure(CrunchError::parse(
+            "0",
+            "invalid unit power"
+        )))
+    );
+    assert!(parse_unit("[m^1/2]").is_err());
+    assert_eq!(
+        parse_unit("[(m^2000000000)^2]"),
+        Err(nom::Err::Failure(CrunchError::parse(
+            "^2]",
+            "unit power out of range"
+        )))
+    );
+    assert!(parse_unit("[m^-2147483648]").is_err());
+}
+
+#[test]
+fn test_parse_conversion() {
+    let converted = |expr, alias| {
+        Ok(AstNode::Conversion {
+            expr: Box::new(expr),
+            unit: UnitExpression::new(alias, 1),
+        })
+    };
+
+    assert_eq!(
+        parse_single_expression("x -> [km]"),
+        converted(AstNode::Name("x".to_string()), "km")
+    );
+    assert_eq!(
+        parse_single_expression("[1 2][m]in[mi]"),
+        Err(CrunchError::parse("in[mi]", "expected end of expression"))
+    );
+    assert_eq!(
+        parse_single_expression("[1 2][m] in [mi]"),
+        converted(
+            AstNode::Vector {
+                value: vec![1.0, 2.0],
+                unit: UnitExpression::new("m", 1)
+            },
+            "mi"
+        )
+    );
+    assert_eq!(
+        parse_line("d = (a * b)->[km];"),
+        Ok(vec![AstNode::Variable {
+            name: Box::new(AstNode::Name("d".to_string())),
+            expr: Box::new(AstNode::Conversion {
+                expr: Box::new(AstNode::Expression {
+                    operation: BinaryOperation::Multiply,
+                    lhs: Box::new(AstNode::Name("a".to_string())),
+                    rhs: Box::new(AstNode::Name("b".to_string()))
+                }),
+                unit: UnitExpression::new("km", 1)
+            })
+        }])
+    );
+}
+
+#[test]
+fn test_parse_constants() {
+    assert_eq!(
+        parse_name("const.k_B)"),
+        Ok((")", AstNode::Name("const.k_B".to_string())))
+    );
+    assert_eq!(
+        parse_name("v_0 "),
+        Ok((" ", AstNode::Name("v_0".to_string())))
+    );
+    assert_eq!(
+        parse_line("override const.g0 = 9.8[m/s^2];"),
+        Ok(vec![AstNode::ConstantOverride {
+            name: "const.g0".to_string(),
+            expr: Box::new(AstNode::Double {
+                value: 9.8,
+                unit: UnitExpression::new("m", 1) / UnitExpression::new("s", 2)
+            })
+        }])
+    );
+    assert!(parse_line("other.c = 1[m];").is_err());
+}
+
+#[test]
+fn test_parse_identifiers() {
+    for name in ["v0", "t_final", "area2", "Δt", "ρ_water", "x_1_b", "units"] {
+        assert_eq!(
+            parse_name(name),
+            Ok(("", AstNode::Name(name.to_string()))),
+            "{}",
+            name
+        );
+    }
+    // a name starts with a letter
+    assert!(parse_name("2x").is_err());
+    assert!(parse_name("_x").is_err());
+    assert_eq!(
+        parse_line("Δt = 2[s]; v0 = (3[m] / Δt);").map(|statements| statements.len()),
+        Ok(2)
+    );
+
+    let error = |line| parse_line(line).unwrap_err().to_string();
+    assert_eq!(
+        error("let x = 1[m];"),
+        "parse error: `let` is a keyword and cannot be used as a name at `let x = 1[m];`"
+    );
+    assert_eq!(
+        error("x = (2 * in);"),
+        "parse error: `in` is a keyword and cannot be used as a name at `in);`"
+    );
+    assert_eq!(
+        error("print(x);"),
+        "parse error: `print` is a keyword and cannot be used as a name at `print(x);`"
+    );
+}
+
+#[test]
+fn test_parse_calls() {
+    let name = |name: &str| AstNode::Name(name.to_string());
+
+    assert_eq!(
+        parse_line("a = atan2(y, (x * 2[m^0]));"),
+        Ok(vec![AstNode::Variable {
+            name: Box::new(name("a")),
+            expr: Box::new(AstNode::Call {
+                function: "atan2".to_string(),
+                arguments: vec![
+                    name("y"),
+                    AstNode::Expression {
                         operation: BinaryOperation::Multiply,
-                        lhs: Box::new(AstNode::Double {
-                            value: Value::Float(2.0),
-                            dimension: Unit { unit: Unit::Meter }
-                        }),
+                        lhs: Box::new(name("x")),
                         rhs: Box::new(AstNode::Double {
-                            value: Value::Float(2.0),
-                            dimension: Unit {
-                                unit: Unit::Kilometer
-                            }
-                        })
-                    })
-                },
-                AstNode::Variable {
-                    name: Box::new(AstNode::Name("y".to_string())),
-                    expr: Box::new(AstNode::Double {
-                        value: Value::Float(1.0),
-                        dimension: Unit {
-                            unit: Unit::Kilometer
-                        }
-                    })
-                },
-                AstNode::Variable {
-                    name: Box::new(AstNode::Name("z".to_string())),
-                    expr: Box::new(AstNode::Expression {
-                        operation: BinaryOperation::Add,
-                        lhs: Box::new(AstNode::Name("x".to_string())),
-                        rhs: Box::new(AstNode::Name("y".to_string()))
-                    })
-                },
-            ]
+                            value: 2.0,
+                            unit: UnitExpression::new("m", 0)
+                        }),
+                    },
+                ],
+            }),
+        }])
+    );
+    assert_eq!(
+        parse_single_expression("(2[m] * sin(30[deg])) -> [cm]"),
+        Ok(AstNode::Conversion {
+            expr: Box::new(AstNode::Expression {
+                operation: BinaryOperation::Multiply,
+                lhs: Box::new(AstNode::Double {
+                    value: 2.0,
+                    unit: UnitExpression::new("m", 1)
+                }),
+                rhs: Box::new(AstNode::Call {
+                    function: "sin".to_string(),
+                    arguments: vec![AstNode::Double {
+                        value: 30.0,
+                        unit: UnitExpression::new("deg", 1)
+                    }],
+                }),
+            }),
+            unit: UnitExpression::new("cm", 1),
+        })
+    );
+    assert!(parse_line("a = sin();").is_err());
+}
+
+#[test]
+fn test_parse_declarations() {
+    assert_eq!(
+        parse_line("dimension Currency; unit USD : Currency; unit EUR = 1.08[USD];"),
+        Ok(vec![
+            AstNode::DimensionDeclaration("Currency".to_string()),
+            AstNode::BaseUnitDeclaration {
+                name: "USD".to_string(),
+                dimension: "Currency".to_string(),
+                prefixable: false,
+            },
+            AstNode::UnitDeclaration {
+                name: "EUR".to_string(),
+                value: Box::new(AstNode::Double {
+                    value: 1.08,
+                    unit: UnitExpression::new("USD", 1)
+                }),
+                prefixable: false,
+            },
+        ])
+    );
+    // `unit` is a keyword, so it cannot be assigned to
+    assert_eq!(
+        parse_line("unit = 1[m];").map_err(|error| error.to_string()),
+        Err(
+            "parse error: `unit` is a keyword and cannot be used as a name at `unit = 1[m];`"
+                .to_string()
+        )
+    );
+    let error = |line| parse_line(line).map_err(|error| error.to_string());
+    assert_eq!(
+        error("unit let = 2[m];"),
+        Err(
+            "parse error: `let` is a keyword and cannot be used as a name at `let = 2[m];`"
+                .to_string()
+        )
+    );
+    assert_eq!(
+        error("unit _ = 2[m];"),
+        Err("parse error: a unit name must start with a letter at `_ = 2[m];`".to_string())
+    );
+    assert_eq!(
+        parse_line("unit EUR = a;").map_err(|error| error.to_string()),
+        Err("parse error: unit values cannot reference variables at `a;`".to_string())
+    );
+
+    assert_eq!(
+        parse_line("prefixable unit B : Information; prefixable unit knot = (1852[m] / 1[h]);"),
+        Ok(vec![
+            AstNode::BaseUnitDeclaration {
+                name: "B".to_string(),
+                dimension: "Information".to_string(),
+                prefixable: true,
+            },
+            AstNode::UnitDeclaration {
+                name: "knot".to_string(),
+                value: Box::new(AstNode::Expression {
+                    operation: BinaryOperation::Divide,
+                    lhs: Box::new(AstNode::Double {
+                        value: 1852.0,
+                        unit: UnitExpression::new("m", 1)
+                    }),
+                    rhs: Box::new(AstNode::Double {
+                        value: 1.0,
+                        unit: UnitExpression::new("h", 1)
+                    }),
+                }),
+                prefixable: true,
+            },
+        ])
+    );
+    assert!(parse_line("prefixable dimension Information;").is_err());
+
+    // a dimension is named like a variable
+    assert_eq!(
+        parse_line("dimension Data_rate2; unit bps : Data_rate2;"),
+        Ok(vec![
+            AstNode::DimensionDeclaration("Data_rate2".to_string()),
+            AstNode::BaseUnitDeclaration {
+                name: "bps".to_string(),
+                dimension: "Data_rate2".to_string(),
+                prefixable: false,
+            },
+        ])
+    );
+    assert_eq!(
+        parse_line("dimension unit;").map_err(|error| error.to_string()),
+        Err("parse error: `unit` is a keyword and cannot be used as a name at `unit;`".to_string())
+    );
+}
+
+#[test]
+fn test_parse_program() {
+    let program = "# a comment on its own line\n\
+                   a = 1[m]; // after a statement\n\
+                   \n\
+                   b = a *\n    2 /* inside */ + 3[m];\n\
+                   /* a block\n   over lines */ c = [1\n 2][m];\n";
+    let statements = parse_program(program).unwrap();
+
+    let lines: Vec<usize> = statements.iter().map(|located| located.line).collect();
+    assert_eq!(lines, vec![2, 4, 7]);
+    assert_eq!(statements[1].text, "b = a *\n    2 /* inside */ + 3[m];");
+    assert_eq!(
+        statements[1].statement,
+        parse_line("b = a * 2 + 3[m];").unwrap()[0]
+    );
+    assert_eq!(
+        parse_line("x = 1[m] # trailing\n;"),
+        parse_line("x = 1[m];")
+    );
+    assert_eq!(
+        parse_single_expression("a // the rest"),
+        Ok(AstNode::Name("a".to_string()))
+    );
+    // a single slash is still a division
+    assert!(parse_line("x = a / b;").is_ok());
+
+    let error = |program| parse_program(program).map_err(|(line, error)| (line, error.to_string()));
+    assert_eq!(
+        error("a = 1[m];\n\nb = 2[m]\nc = 3[m];"),
+        Err((
+            3,
+            "parse error: expected `name = expression;` at `b = 2[m]`".to_string()
         ))
     );
+    assert_eq!(
+        error("a = 1[m];\n/* never closed\nb = 2[m];"),
+        Err((
+            2,
+            "parse error: unterminated comment, expected `*/` at `/* never closed`".to_string()
+        ))
+    );
+    // a failure inside a statement is reported on the line it was found on
+    assert_eq!(
+        error("a = 1[m];\nb = a\n  /* never closed;").map_err(|(line, _)| line),
+        Err(3)
+    );
 }
@@ -0,0 +1,197 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Neg;
+
+/// An exact fraction, used for the exponents of dimensions and units so that
+/// e.g. `m^(1/2)` can be represented. Always kept in lowest terms with a
+/// positive denominator.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub struct Rational {
+    numerator: i32,
+    denominator: i32,
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    match b {
+        0 => a.abs(),
+        _ => gcd(b, a % b),
+    }
+}
+
+impl Rational {
+    /// Returns `None` when `denominator` is zero.
+    pub fn new(numerator: i32, denominator: i32) -> Option<Self> {
+        Rational::reduce(numerator.into(), denominator.into())
+    }
+
+    /// The fraction in lowest terms, or `None` when the denominator is zero or
+    /// a part does not fit in an `i32`. `i32::MIN` is left out as well, so
+    /// that every rational can be negated.
+    fn reduce(numerator: i64, denominator: i64) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+        let divisor = gcd(numerator, denominator) * denominator.signum();
+        let part = |value: i64| {
+            i32::try_from(value / divisor)
+                .ok()
+                .filter(|value| *value != i32::MIN)
+        };
+        Some(Rational {
+            numerator: part(numerator)?,
+            denominator: part(denominator)?,
+        })
+    }
+
+    /// Sums, differences, products and quotients are checked, since exponents
+    /// come from the user and e.g. `m^2000000000 * m^2000000000` overflows.
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        let (a, b, c, d) = self.parts(rhs);
+        Rational::reduce(a * d + c * b, b * d)
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.checked_add(-rhs)
+    }
+
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        let (a, b, c, d) = self.parts(rhs);
+        Rational::reduce(a * c, b * d)
+    }
+
+    /// `None` as well when dividing by zero.
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        let (a, b, c, d) = self.parts(rhs);
+        Rational::reduce(a * d, b * c)
+    }
+
+    /// Both fractions as `i64`, in which products of the parts cannot overflow.
+    fn parts(self, rhs: Self) -> (i64, i64, i64, i64) {
+        (
+            self.numerator.into(),
+            self.denominator.into(),
+            rhs.numerator.into(),
+            rhs.denominator.into(),
+        )
+    }
+
+    pub const fn integer(value: i32) -> Self {
+        Rational {
+            numerator: value,
+            denominator: 1,
+        }
+    }
+
+    pub const ZERO: Rational = Rational::integer(0);
+    pub const ONE: Rational = Rational::integer(1);
+
+    pub fn numerator(&self) -> i32 {
+        self.numerator
+    }
+
+    pub fn denominator(&self) -> i32 {
+        self.denominator
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.denominator == 1
+    }
+
+    /// The fraction with the smallest denominator, up to 100, which equals
+    /// `value`, e.g. `1/2` for `0.5`. `None` if there is none.
+    pub fn approximate(value: f64) -> Option<Self> {
+        (1..=100).find_map(|denominator| {
+            let numerator = value * denominator as f64;
+            let rounded = numerator.round();
+            let exact = (numerator - rounded).abs() < 1e-9 * denominator as f64;
+            match exact && rounded.abs() < i32::MAX as f64 {
+                true => Rational::new(rounded as i32, denominator),
+                false => None,
+            }
+        })
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+}
+
+impl Default for Rational {
+    fn default() -> Self {
+        Rational::ZERO
+    }
+}
+
+impl From<i32> for Rational {
+    fn from(value: i32) -> Self {
+        Rational::integer(value)
+    }
+}
+
+impl Neg for Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Self {
+        Rational {
+            numerator: -self.numerator,
+            denominator: self.denominator,
+        }
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (a, b, c, d) = self.parts(*other);
+        (a * d).cmp(&(c * b))
+    }
+}
+
+/// Writes integers plainly and fractions as `1/2`.
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.denominator {
+            1 => write!(f, "{}", self.numerator),
+            _ => write!(f, "{}/{}", self.numerator, self.denominator),
+        }
+    }
+}
+
+#[test]
+fn test_rational() {
+    let half = Rational::new(1, 2).unwrap();
+
+    assert_eq!(Rational::new(2, 4), Some(half));
+    assert_eq!(Rational::new(-3, -6), Some(half));
+    assert_eq!(Rational::new(3, -6), Some(-half));
+    assert_eq!(Rational::new(1, 0), None);
+    assert_eq!(half.checked_add(half), Some(Rational::ONE));
+    assert_eq!(half.checked_mul(Rational::from(4)), Some(Rational::from(2)));
+    assert_eq!(
+        Rational::ONE.checked_div(Rational::from(-3)),
+        Rational::new(-1, 3)
+    );
+    assert_eq!(half.checked_sub(Rational::ONE), Some(-half));
+    assert_eq!(half.checked_div(Rational::ZERO), None);
+    assert!(half > Rational::ZERO && -half < Rational::ZERO);
+    assert_eq!(half.to_string(), "1/2");
+    assert_eq!(Rational::from(-2).to_string(), "-2");
+
+    assert_eq!(Rational::approximate(0.5), Some(half));
+    assert_eq!(Rational::approximate(-3.0), Some(Rational::from(-3)));
+    assert_eq!(Rational::approximate(1.0 / 3.0), Rational::new(1, 3));
+    assert_eq!(Rational::approximate(std::f64::consts::PI), None);
+
+    // overflow is reported rather than wrapping around
+    let large = Rational::from(2_000_000_000);
+    assert_eq!(large.checked_add(large), None);
+    assert_eq!(large.checked_mul(Rational::from(2)), None);
+    assert_eq!(Rational::new(1, i32::MAX).unwrap().checked_div(large), None);
+    assert_eq!(Rational::new(i32::MIN, 1), None);
+    assert!(Rational::new(1, i32::MAX).unwrap() < Rational::new(1, i32::MAX - 1).unwrap());
+}
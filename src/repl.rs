@@ -0,0 +1,301 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+
+use super::error::CrunchError;
+use super::interpreter::{Interpreter, Memory};
+use super::output::{self, OutputFormat};
+use super::parser::*;
+use super::types::*;
+use super::units::UnitRegistry;
+
+const HELP: &str = "\
+Enter assignments such as `a = (2[km] * 3[m]);`, or an expression to print it.
+Convert a result with `a -> [m^2]` or `a in [km^2]`.
+
+:vars           list every variable
+:unit <unit>    show what a unit such as `kWh` or `kg*m/s^2` converts to
+:clear          forget every variable
+:save <file>    write the session's statements to a file
+:load <file>    run a file in this session
+:help           show this message
+:quit           leave the repl";
+
+/// Completes variable names, and unit aliases inside `[...]`.
+struct CrunchHelper {
+    variables: Vec<String>,
+    units: Vec<String>,
+}
+
+impl Completer for CrunchHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let before = &line[..pos];
+        // names may contain underscores and letters of any script, e.g. `ρ_water`
+        let start = before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !(c.is_alphanumeric() || *c == '_'))
+            .map(|(index, c)| index + c.len_utf8())
+            .unwrap_or(0);
+        let word = &before[start..];
+
+        let in_unit = before[..start].rfind('[') > before[..start].rfind(']');
+        let candidates: Vec<String> = match in_unit {
+            true => self.units.clone(),
+            false => self.variables.clone(),
+        };
+
+        Ok((
+            start,
+            candidates
+                .into_iter()
+                .filter(|candidate| candidate.starts_with(word))
+                .collect(),
+        ))
+    }
+}
+
+impl Hinter for CrunchHelper {
+    type Hint = String;
+}
+
+impl Highlighter for CrunchHelper {}
+
+impl Validator for CrunchHelper {}
+
+impl Helper for CrunchHelper {}
+
+/// The state kept between lines: the bindings, and the source of every line
+/// which ran successfully so that `:save` can write it back out.
+struct Session {
+    memory: Memory,
+    source: Vec<String>,
+    /// The units the session started with, restored by `:clear`.
+    units: UnitRegistry,
+}
+
+impl Session {
+    fn new(units: UnitRegistry) -> Self {
+        Session {
+            memory: Memory::with_units(vec![], units.clone()),
+            source: vec![],
+            units,
+        }
+    }
+
+    /// Runs a line of statements, or prints the value of a bare expression.
+    fn input(&mut self, line: &str) -> Result<Option<String>, CrunchError> {
+        let statements = match parse_line(line) {
+            Ok(statements) => statements,
+            Err(statement_error) => {
+                if line.contains('=') {
+                    return Err(statement_error);
+                }
+                let expression = parse_single_expression(line)?;
+                let value = self.memory.evaluate(expression)?;
+                return Ok(Some(output::render_value(&value, OutputFormat::Table)));
+            }
+        };
+
+        let names: Vec<String> = statements
+            .iter()
+            .filter_map(|statement| match statement {
+                AstNode::Variable { name, .. } => match name.as_ref() {
+                    AstNode::Name(name) => Some(name.clone()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+
+        self.memory.execute(statements)?;
+        self.source.push(line.to_string());
+
+        let values: Vec<(String, Value)> = names
+            .into_iter()
+            .map(|name| {
+                let value = self.memory.memory[&name].clone();
+                (name, value)
+            })
+            .collect();
+        Ok(Some(output::render_values(&values, OutputFormat::Table)))
+    }
+
+    fn vars(&self) -> String {
+        output::render_values(&self.memory.values(), OutputFormat::Table)
+    }
+
+    fn load(&mut self, path: &str) -> Result<String, String> {
+        let contents = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+        let statements = parse_program(&contents)
+            .map_err(|(line, error)| format!("{}:{}: {}", path, line, error))?;
+        for Located {
+            line,
+            text,
+            statement,
+        } in statements
+        {
+            self.memory
+                .execute(vec![statement])
+                .map_err(|error| format!("{}:{}: {}", path, line, error))?;
+            self.source.push(text.to_string());
+        }
+        Ok(self.vars())
+    }
+
+    fn save(&self, path: &str) -> Result<String, String> {
+        let contents: String = self
+            .source
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect();
+        fs::write(path, contents).map_err(|error| format!("{}: {}", path, error))?;
+        Ok(format!("saved {} line(s) to {}\n", self.source.len(), path))
+    }
+
+    /// Handles a `:command`, returning `None` when the repl should exit.
+    fn command(&mut self, line: &str) -> Option<Result<String, String>> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("");
+        let argument = words.next();
+
+        Some(match (command, argument) {
+            (":quit" | ":q", _) => return None,
+            (":help", _) => Ok(format!("{}\n", HELP)),
+            (":vars", _) => Ok(self.vars()),
+            (":clear", _) => {
+                *self = Session::new(self.units.clone());
+                Ok(String::new())
+            }
+            (":unit", Some(name)) => parse_unit_annotation(name)
+                .and_then(|unit| self.memory.resolve_unit(&unit))
+                .map(|unit| describe_unit(name, &unit))
+                .map_err(|error| error.to_string()),
+            (":save", Some(path)) => self.save(path),
+            (":load", Some(path)) => self.load(path),
+            (":unit" | ":save" | ":load", None) => {
+                Err(format!("{} needs an argument, see :help", command))
+            }
+            _ => Err(format!("unknown command {}, see :help", command)),
+        })
+    }
+}
+
+/// `km = 1000 m`, or with the offset of a temperature scale, `degC = 1 K +
+/// 273.15 K`.
+fn describe_unit(name: &str, unit: &Unit) -> String {
+    match unit.offset {
+        Some(offset) if offset != 0.0 => format!(
+            "{} = {} {} + {} {}\n",
+            name, unit.conversion_factor, unit.dimension, offset, unit.dimension
+        ),
+        _ => format!("{} = {} {}\n", name, unit.conversion_factor, unit.dimension),
+    }
+}
+
+fn history_file() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".crunch_history"))
+}
+
+/// Reads lines until end of input, evaluating each against the same `Memory`.
+pub fn repl(units: UnitRegistry) -> Result<(), ReadlineError> {
+    let mut editor: Editor<CrunchHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(CrunchHelper {
+        variables: vec![],
+        units: vec![],
+    }));
+    if let Some(path) = history_file() {
+        // there is no history the first time the repl is started
+        let _ = editor.load_history(&path);
+    }
+
+    let mut session = Session::new(units);
+    loop {
+        if let Some(helper) = editor.helper_mut() {
+            helper.variables = session.memory.variables();
+            helper.units = session.memory.units.aliases();
+        }
+
+        let line = match editor.readline("crunch> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(error) => return Err(error),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line)?;
+
+        let result = match line.starts_with(':') {
+            true => match session.command(line) {
+                Some(result) => result,
+                None => break,
+            },
+            false => session
+                .input(line)
+                .map(|output| output.unwrap_or_default())
+                .map_err(|error| error.to_string()),
+        };
+        match result {
+            Ok(output) => print!("{}", output),
+            Err(error) => eprintln!("error: {}", error),
+        }
+    }
+
+    if let Some(path) = history_file() {
+        editor.save_history(&path)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_session() {
+    let mut session = Session::new(UnitRegistry::builtin());
+
+    assert_eq!(
+        session.input("a = 2[km^1];"),
+        Ok(Some("a = 2000 m\n".to_string()))
+    );
+    assert_eq!(
+        session.input("(a * 3[m^1])"),
+        Ok(Some("6000 m^2\n".to_string()))
+    );
+    assert!(session.input("b = (a + 1[m^2]);").is_err());
+    assert_eq!(
+        session.command(":vars"),
+        Some(Ok("a = 2000 m\n".to_string()))
+    );
+    assert_eq!(
+        session.command(":unit km"),
+        Some(Ok("km = 1000 m\n".to_string()))
+    );
+    assert_eq!(
+        session.command(":unit degC"),
+        Some(Ok("degC = 1 K + 273.15 K\n".to_string()))
+    );
+    assert_eq!(
+        session.command(":unit K"),
+        Some(Ok("K = 1 K\n".to_string()))
+    );
+
+    session.command(":clear");
+    assert!(session.memory.variables().is_empty());
+    assert_eq!(session.command(":quit"), None);
+}
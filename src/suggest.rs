@@ -0,0 +1,72 @@
+/// The edit distance between `a` and `b`, counting an insertion, a deletion,
+/// a substitution or a swap of two neighbouring characters as one edit, so
+/// that `kilometre` is one edit from `kilometer`.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // distances[i][j] is the distance between a[..i] and b[..j]
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + substitution);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = best;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// The candidate closest to `name`, for a "did you mean" hint. A match which
+/// only differs in case wins, otherwise the candidate with the fewest edits,
+/// allowing about one edit per three characters. Ties go to the candidate
+/// which sorts first, so the suggestion never depends on candidate order.
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let lowercase = name.to_lowercase();
+    let allowed = (name.chars().count() / 3).max(1);
+
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| {
+            let edits = match candidate.to_lowercase() == lowercase {
+                true => 0,
+                false => distance(name, candidate),
+            };
+            (edits, candidate)
+        })
+        .filter(|(edits, _)| *edits <= allowed)
+        .min()
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+#[test]
+fn test_distance() {
+    assert_eq!(distance("kilometre", "kilometer"), 1);
+    assert_eq!(distance("metre", "meter"), 1);
+    assert_eq!(distance("speed", "sped"), 1);
+    assert_eq!(distance("", "abc"), 3);
+    assert_eq!(distance("kitten", "sitting"), 3);
+}
+
+#[test]
+fn test_closest() {
+    let units = ["m", "meter", "meters", "km", "kilometer", "Pa", "pascal"];
+
+    assert_eq!(closest("kilometre", units), Some("kilometer".to_string()));
+    assert_eq!(closest("metres", units), Some("meters".to_string()));
+    assert_eq!(closest("KM", units), Some("km".to_string()));
+    assert_eq!(closest("PA", units), Some("Pa".to_string()));
+    assert_eq!(closest("furlong", units), None);
+    // a single character is allowed one edit
+    assert_eq!(closest("n", ["m", "mm"]), Some("m".to_string()));
+}
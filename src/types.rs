@@ -1,17 +1,9 @@
-extern crate dimensioned as dim;
-extern crate uom;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
 
-use std::collections::HashMap;
-use std::ops::{Add, Div, Mul, Sub};
-
-use uom::marker;
-use uom::si::area::square_meter;
-use uom::si::f64::{Area, Length, Mass, Time};
-use uom::si::length::{kilometer, meter};
-use uom::si::mass::kilogram;
-use uom::si::time::second;
-use uom::si::{Dimension, Quantity, SI};
-use uom::typenum::{N1, P1};
+use super::error::{CrunchError, POWER_OUT_OF_RANGE as OVERFLOW};
+use super::rational::Rational;
 
 #[derive(PartialEq, PartialOrd, Debug, Clone)]
 pub enum BinaryOperation {
@@ -19,282 +11,829 @@ pub enum BinaryOperation {
     Subtract,
     Multiply,
     Divide,
+    Power,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum UnaryOperation {
+    Plus,
+    Minus,
+}
+
+/// Number of SI base dimensions tracked by `Dimension`.
+pub const BASE_DIMENSIONS: usize = 7;
+
+/// Symbols of the SI base units, in the same order as `Dimension::exponents`.
+pub const BASE_UNIT_SYMBOLS: [&str; BASE_DIMENSIONS] = ["m", "kg", "s", "A", "K", "mol", "cd"];
+
+/// A base dimension declared by a program, e.g. `dimension Currency;`,
+/// together with the symbol of its base unit, e.g. `USD`.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
+pub struct BaseDimension {
+    pub name: String,
+    pub symbol: String,
+}
+
+/// The dimension of a quantity as exponents over the seven SI base dimensions:
+/// length, mass, time, electric current, temperature, amount of substance and
+/// luminous intensity, and over any base dimensions declared by the program.
+/// Exponents are rational so that e.g. the square root of a frequency can be
+/// represented.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Dimension {
+    pub exponents: [Rational; BASE_DIMENSIONS],
+    /// Exponents over declared base dimensions; none of them is zero.
+    pub declared: BTreeMap<BaseDimension, Rational>,
+}
+
+impl Dimension {
+    pub fn dimensionless() -> Self {
+        Dimension::default()
+    }
+
+    fn base(index: usize) -> Self {
+        let mut dimension = Dimension::default();
+        dimension.exponents[index] = Rational::ONE;
+        dimension
+    }
+
+    /// The dimension of a declared base dimension, e.g. `Currency`.
+    pub fn declared(base: BaseDimension) -> Self {
+        Dimension {
+            declared: BTreeMap::from([(base, Rational::ONE)]),
+            ..Dimension::default()
+        }
+    }
+
+    /// Plane angle, a built in declared base dimension with the base unit
+    /// `rad`, so that angles are told apart from plain numbers.
+    pub fn angle() -> Self {
+        Dimension::declared(BaseDimension {
+            name: "Angle".to_string(),
+            symbol: "rad".to_string(),
+        })
+    }
+
+    /// The dimension with any power of angle removed, e.g. `m` for `m*rad`.
+    pub fn without_angle(mut self) -> Self {
+        self.declared.retain(|base, _| base.name != "Angle");
+        self
+    }
+
+    pub fn length() -> Self {
+        Dimension::base(0)
+    }
+
+    pub fn mass() -> Self {
+        Dimension::base(1)
+    }
+
+    pub fn time() -> Self {
+        Dimension::base(2)
+    }
+
+    pub fn current() -> Self {
+        Dimension::base(3)
+    }
+
+    pub fn temperature() -> Self {
+        Dimension::base(4)
+    }
+
+    pub fn amount() -> Self {
+        Dimension::base(5)
+    }
+
+    pub fn luminous_intensity() -> Self {
+        Dimension::base(6)
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.exponents
+            .iter()
+            .all(|exponent| *exponent == Rational::ZERO)
+            && self.declared.is_empty()
+    }
+
+    /// Raises the dimension to `power`, or `None` when an exponent overflows.
+    pub fn checked_pow(self, power: Rational) -> Option<Self> {
+        let mut exponents = self.exponents;
+        for exponent in exponents.iter_mut() {
+            *exponent = exponent.checked_mul(power)?;
+        }
+        let mut declared = BTreeMap::new();
+        for (base, exponent) in self.declared {
+            let exponent = exponent.checked_mul(power)?;
+            if exponent != Rational::ZERO {
+                declared.insert(base, exponent);
+            }
+        }
+        Some(Dimension {
+            exponents,
+            declared,
+        })
+    }
+
+    /// Multiplying quantities adds the exponents of their dimensions. `None`
+    /// when an exponent overflows.
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        let mut exponents = self.exponents;
+        for (exponent, rhs_exponent) in exponents.iter_mut().zip(rhs.exponents) {
+            *exponent = exponent.checked_add(rhs_exponent)?;
+        }
+        let mut declared = self.declared;
+        for (base, rhs_exponent) in rhs.declared {
+            let exponent = declared
+                .get(&base)
+                .unwrap_or(&Rational::ZERO)
+                .checked_add(rhs_exponent)?;
+            match exponent == Rational::ZERO {
+                true => declared.remove(&base),
+                false => declared.insert(base, exponent),
+            };
+        }
+        Some(Dimension {
+            exponents,
+            declared,
+        })
+    }
+
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        self.checked_mul(rhs.checked_pow(Rational::from(-1))?)
+    }
+
+    /// Like `checked_pow`, for the built in dimensions, whose exponents are
+    /// small. Panics on overflow.
+    pub fn pow(self, power: Rational) -> Self {
+        self.checked_pow(power).expect(OVERFLOW)
+    }
+
+    pub fn powi(self, power: i32) -> Self {
+        self.pow(power.into())
+    }
+
+    /// The name of the kind of quantity with this dimension, if it has one.
+    pub fn name(&self) -> Option<&'static str> {
+        let names = [
+            ("Dimensionless", Dimension::dimensionless()),
+            ("Length", Dimension::length()),
+            ("Mass", Dimension::mass()),
+            ("Time", Dimension::time()),
+            ("Current", Dimension::current()),
+            ("Temperature", Dimension::temperature()),
+            ("Amount", Dimension::amount()),
+            ("Luminous intensity", Dimension::luminous_intensity()),
+            ("Area", Dimension::length().powi(2)),
+            ("Volume", Dimension::length().powi(3)),
+            ("Frequency", Dimension::time().powi(-1)),
+            ("Velocity", Dimension::length() / Dimension::time()),
+            (
+                "Acceleration",
+                Dimension::length() / Dimension::time().powi(2),
+            ),
+            (
+                "Force",
+                Dimension::mass() * Dimension::length() / Dimension::time().powi(2),
+            ),
+            (
+                "Pressure",
+                Dimension::mass() / Dimension::length() / Dimension::time().powi(2),
+            ),
+            (
+                "Energy",
+                Dimension::mass() * Dimension::length().powi(2) / Dimension::time().powi(2),
+            ),
+            (
+                "Power",
+                Dimension::mass() * Dimension::length().powi(2) / Dimension::time().powi(3),
+            ),
+            ("Charge", Dimension::current() * Dimension::time()),
+        ];
+        names
+            .iter()
+            .find(|(_, dimension)| dimension == self)
+            .map(|(name, _)| *name)
+    }
+
+    /// Describes the dimension for error messages, e.g. `Area (m^2)` or
+    /// `Currency (USD)`.
+    pub fn describe(&self) -> String {
+        let declared_name = match self.declared.iter().next() {
+            Some((base, _)) if *self == Dimension::declared(base.clone()) => Some(&base.name[..]),
+            _ => None,
+        };
+        match self.name().or(declared_name) {
+            Some(name) => format!("{} ({})", name, self),
+            None => format!("({})", self),
+        }
+    }
+
+    /// The coherent SI derived unit named after this dimension, if there is one.
+    ///
+    /// Only units which stand for several base units are listed: `Hz` would
+    /// read `1/s` back as a frequency when it is as often a decay rate or an
+    /// angular velocity.
+    pub fn derived_unit(&self) -> Option<&'static str> {
+        let force = || Dimension::mass() * Dimension::length() / Dimension::time().powi(2);
+        let power = || force() * Dimension::length() / Dimension::time();
+        let charge = || Dimension::current() * Dimension::time();
+        let voltage = || power() / Dimension::current();
+        let units = [
+            ("N", force()),
+            ("Pa", force() / Dimension::length().powi(2)),
+            ("J", force() * Dimension::length()),
+            ("W", power()),
+            ("C", charge()),
+            ("V", voltage()),
+            ("F", charge() / voltage()),
+            ("Ω", voltage() / Dimension::current()),
+        ];
+        units
+            .iter()
+            .find(|(_, dimension)| dimension == self)
+            .map(|(symbol, _)| *symbol)
+    }
+
+    /// Writes the dimension the way values are shown: as a named derived unit
+    /// when one matches exactly, e.g. `J` rather than `kg*m^2/s^2`, and in base
+    /// units otherwise. Partial matches such as `J/K` are not attempted, since
+    /// the choice between e.g. `N*m` and `J` depends on what is being measured.
+    pub fn canonical(&self) -> String {
+        match self.derived_unit() {
+            Some(symbol) => symbol.to_string(),
+            None => self.to_string(),
+        }
+    }
+}
+
+/// Panics on overflow, see `Dimension::pow`.
+impl Mul for Dimension {
+    type Output = Dimension;
+
+    fn mul(self, rhs: Self) -> Self {
+        self.checked_mul(rhs).expect(OVERFLOW)
+    }
+}
+
+impl Div for Dimension {
+    type Output = Dimension;
+
+    fn div(self, rhs: Self) -> Self {
+        self.checked_div(rhs).expect(OVERFLOW)
+    }
+}
+
+/// Writes `symbol^exponent`, leaving out an exponent of 1 and parenthesising
+/// fractional ones, e.g. `m`, `s^2` or `Hz^(1/2)`.
+fn power(symbol: &str, exponent: Rational) -> String {
+    match (exponent == Rational::ONE, exponent.is_integer()) {
+        (true, _) => symbol.to_string(),
+        (false, true) => format!("{}^{}", symbol, exponent),
+        (false, false) => format!("{}^({})", symbol, exponent),
+    }
+}
+
+/// Writes a product of powers as a quotient with positive exponents, e.g.
+/// `kg*m/s^2` or `W/(m^2*K)`.
+fn write_quotient<'a>(
+    f: &mut fmt::Formatter,
+    factors: impl Iterator<Item = (&'a str, Rational)> + Clone,
+) -> fmt::Result {
+    let numerator: Vec<String> = factors
+        .clone()
+        .filter(|(_, exponent)| *exponent > Rational::ZERO)
+        .map(|(symbol, exponent)| power(symbol, exponent))
+        .collect();
+    let denominator: Vec<String> = factors
+        .filter(|(_, exponent)| *exponent < Rational::ZERO)
+        .map(|(symbol, exponent)| power(symbol, -exponent))
+        .collect();
+
+    let numerator = match numerator.is_empty() {
+        true => "1".to_string(),
+        false => numerator.join("*"),
+    };
+    match denominator.len() {
+        0 => write!(f, "{}", numerator),
+        1 => write!(f, "{}/{}", numerator, denominator[0]),
+        _ => write!(f, "{}/({})", numerator, denominator.join("*")),
+    }
+}
+
+/// Writes the dimension in base units, e.g. `kg*m/s^2`.
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // mass first, so that force reads kg*m/s^2 rather than m*kg/s^2
+        let order = [1, 0, 2, 3, 4, 5, 6];
+        write_quotient(
+            f,
+            self.declared
+                .iter()
+                .map(|(base, exponent)| (&base.symbol[..], *exponent))
+                .chain(
+                    order
+                        .iter()
+                        .map(|index| (BASE_UNIT_SYMBOLS[*index], self.exponents[*index])),
+                ),
+        )
+    }
+}
+
+/// A unit as written in a `[unit^n]` annotation: the factor which converts a
+/// number in this unit into SI base units, and the dimension it measures.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Unit {
+    pub conversion_factor: f64,
+    pub dimension: Dimension,
+    /// Set for the scales of absolute temperature, `K`, `degC` and `degF`, to
+    /// what is added after scaling to reach kelvin, e.g. 273.15 for `degC`.
+    /// Only a unit written on its own keeps it; in `J/K` or `K^2` the scale
+    /// measures temperature differences.
+    pub offset: Option<f64>,
+}
+
+impl Unit {
+    pub fn new(conversion_factor: f64, dimension: Dimension) -> Self {
+        Unit {
+            conversion_factor,
+            dimension,
+            offset: None,
+        }
+    }
+
+    /// A scale of absolute temperature.
+    pub fn absolute(conversion_factor: f64, offset: f64) -> Self {
+        Unit {
+            offset: Some(offset),
+            ..Unit::new(conversion_factor, Dimension::temperature())
+        }
+    }
+
+    /// `None` when an exponent of the dimension overflows.
+    pub fn checked_pow(self, power: Rational) -> Option<Self> {
+        Some(Unit {
+            conversion_factor: pow(self.conversion_factor, power),
+            dimension: self.dimension.checked_pow(power)?,
+            offset: self.offset.filter(|_| power == Rational::ONE),
+        })
+    }
+
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        Some(Unit {
+            conversion_factor: self.conversion_factor * rhs.conversion_factor,
+            dimension: self.dimension.checked_mul(rhs.dimension)?,
+            offset: None,
+        })
+    }
+
+    /// Panics on overflow, see `Dimension::pow`.
+    pub fn pow(self, power: Rational) -> Self {
+        self.checked_pow(power).expect(OVERFLOW)
+    }
+
+    pub fn powi(self, power: i32) -> Self {
+        self.pow(power.into())
+    }
 }
 
-type Q<D, U> = Quantity<D, U, f64>;
-
-fn scalar_vector_addition<D, U>(scalar: Q<D, U>, vector: Vec<Q<D, U>>) -> Vec<Q<D, U>>
-where
-    D: uom::si::Dimension + ?Sized,
-    U: uom::si::Units<f64> + ?Sized,
-    <D as Dimension>::Kind: marker::Add,
-{
-    vector.iter().map(|left_x| *left_x + scalar).collect()
-}
-
-fn scalar_vector_subtraction<D, U>(scalar: Q<D, U>, vector: Vec<Q<D, U>>) -> Vec<Q<D, U>>
-where
-    D: uom::si::Dimension + ?Sized,
-    U: uom::si::Units<f64> + ?Sized,
-    <D as Dimension>::Kind: marker::Sub,
-{
-    vector.iter().map(|left_x| *left_x - scalar).collect()
-}
-
-fn scalar_vector_multiplication<D1, D2, U>(
-    scalar: Q<D1, U>,
-    vector: Vec<Q<D2, U>>,
-) -> Vec<Q<<D1 as Mul<D2>>::Output, U>>
-where
-    D1: Dimension + Mul<D2>,
-    D2: Dimension,
-    U: uom::si::Units<f64> + ?Sized,
-    <D1 as Mul<D2>>::Output: Dimension,
-    <D2 as Dimension>::L: std::ops::Add<<D1 as Dimension>::L>,
-    <D2 as Dimension>::M: std::ops::Add<<D1 as Dimension>::M>,
-    <D2 as Dimension>::T: std::ops::Add<<D1 as Dimension>::T>,
-    <D2 as Dimension>::I: std::ops::Add<<D1 as Dimension>::I>,
-    <D2 as Dimension>::Th: std::ops::Add<<D1 as Dimension>::Th>,
-    <D2 as Dimension>::N: std::ops::Add<<D1 as Dimension>::N>,
-    <D2 as Dimension>::J: std::ops::Add<<D1 as Dimension>::J>,
-    <D2 as Dimension>::Kind: marker::Mul,
-    <D1 as Dimension>::Kind: marker::Mul,
-{
-    vector.iter().map(|left_x| *left_x * scalar).collect()
-}
-
-fn scalar_vector_division<D1, D2, U>(
-    scalar: Q<D1, U>,
-    vector: Vec<Q<D2, U>>,
-) -> Vec<Q<<D1 as Div<D2>>::Output, U>>
-where
-    D1: Dimension + Div<D2>,
-    D2: Dimension,
-    U: uom::si::Units<f64> + ?Sized,
-    <D1 as Div<D2>>::Output: Dimension,
-{
-    vector.iter().map(|left_x| *left_x / scalar).collect()
-}
-
-fn elementwise_vector_addition<D, U>(vector1: Vec<Q<D, U>>, vector2: Vec<Q<D, U>>) -> Vec<Q<D, U>>
-where
-    D: uom::si::Dimension + ?Sized,
-    U: uom::si::Units<f64> + ?Sized,
-{
-    vector1
-        .iter()
-        .zip(vector2)
-        .map(|(left_x, right_x)| *left_x + right_x)
-        .collect()
-}
-
-fn elementwise_vector_subtraction<D, U>(
-    vector1: Vec<Q<D, U>>,
-    vector2: Vec<Q<D, U>>,
-) -> Vec<Q<D, U>>
-where
-    D: uom::si::Dimension + ?Sized,
-    U: uom::si::Units<f64> + ?Sized,
-{
-    vector1
-        .iter()
-        .zip(vector2)
-        .map(|(left_x, right_x)| *left_x - right_x)
-        .collect()
-}
-
-fn elementwise_vector_multiplication<D1, D2, U>(
-    vector1: Vec<Q<D1, U>>,
-    vector2: Vec<Q<D2, U>>,
-) -> Vec<Q<<D1 as Mul<D2>>::Output, U>>
-where
-    D1: Dimension + Mul<D2>,
-    D2: Dimension,
-    U: uom::si::Units<f64> + ?Sized,
-    <D1 as Mul<D2>>::Output: Dimension,
-{
-    vector1
-        .iter()
-        .zip(vector2)
-        .map(|(left_x, right_x)| *left_x * right_x)
-        .collect()
-}
-
-fn elementwise_vector_division<D1, D2, U>(
-    vector1: Vec<Q<D1, U>>,
-    vector2: Vec<Q<D2, U>>,
-) -> Vec<Q<<D1 as Div<D2>>::Output, U>>
-where
-    D1: Dimension + Div<D2>,
-    D2: Dimension,
-    U: uom::si::Units<f64> + ?Sized,
-    <D1 as Div<D2>>::Output: Dimension,
-{
-    vector1
-        .iter()
-        .zip(vector2)
-        .map(|(left_x, right_x)| *left_x / right_x)
-        .collect()
-}
-
-pub enum Unit {
-    Meter,
-    Kilometer,
+/// Panics on overflow, see `Dimension::pow`.
+impl Mul for Unit {
+    type Output = Unit;
+
+    fn mul(self, rhs: Self) -> Self {
+        self.checked_mul(rhs).expect(OVERFLOW)
+    }
+}
+
+/// Raises `x` to `power`, exactly as `powi` does for whole powers.
+fn pow(x: f64, power: Rational) -> f64 {
+    match power.is_integer() {
+        true => x.powi(power.numerator()),
+        false => x.powf(power.to_f64()),
+    }
+}
+
+/// Raised when adding or subtracting quantities of different dimensions.
+#[derive(PartialEq, Debug, Clone)]
+pub struct DimensionMismatch {
+    pub operation: BinaryOperation,
+    pub lhs: Dimension,
+    pub rhs: Dimension,
 }
 
+impl fmt::Display for DimensionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.operation {
+            BinaryOperation::Subtract => write!(
+                f,
+                "cannot subtract {} from {}",
+                self.rhs.describe(),
+                self.lhs.describe()
+            ),
+            _ => write!(
+                f,
+                "cannot add {} to {}",
+                self.lhs.describe(),
+                self.rhs.describe()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DimensionMismatch {}
+
+/// A unit annotation as written, e.g. `[km^2]` or `[W/(m^2*K)]`, as the
+/// product of unit aliases raised to powers: `W^1 * m^-2 * K^-1`. It is
+/// turned into a `Unit` by the interpreter's unit registry.
+#[derive(PartialEq, Debug, Clone)]
+pub struct UnitExpression {
+    pub factors: Vec<(String, Rational)>,
+}
+
+impl UnitExpression {
+    pub fn new(alias: &str, power: i32) -> Self {
+        UnitExpression::product(&[(alias, power)])
+    }
+
+    pub fn product(factors: &[(&str, i32)]) -> Self {
+        UnitExpression {
+            factors: factors
+                .iter()
+                .map(|(alias, power)| (alias.to_string(), Rational::from(*power)))
+                .collect(),
+        }
+    }
+
+    /// `None` when a power overflows, e.g. in `[(m^2000000000)^2]`.
+    pub fn checked_pow(self, power: Rational) -> Option<Self> {
+        let factors = self
+            .factors
+            .into_iter()
+            .map(|(alias, exponent)| Some((alias, exponent.checked_mul(power)?)))
+            .collect::<Option<_>>()?;
+        Some(UnitExpression { factors })
+    }
+
+    /// Panics on overflow, see `Dimension::pow`.
+    pub fn pow(self, power: Rational) -> Self {
+        self.checked_pow(power).expect(OVERFLOW)
+    }
+
+    pub fn powi(self, power: i32) -> Self {
+        self.pow(power.into())
+    }
+}
+
+impl Mul for UnitExpression {
+    type Output = UnitExpression;
+
+    fn mul(self, rhs: Self) -> Self {
+        let mut factors = self.factors;
+        factors.extend(rhs.factors);
+        UnitExpression { factors }
+    }
+}
+
+impl Div for UnitExpression {
+    type Output = UnitExpression;
+
+    fn div(self, rhs: Self) -> Self {
+        self * rhs.powi(-1)
+    }
+}
+
+/// Writes the annotation without brackets, e.g. `kg*m/s^2`.
+impl fmt::Display for UnitExpression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_quotient(
+            f,
+            self.factors
+                .iter()
+                .map(|(alias, exponent)| (alias.as_str(), *exponent)),
+        )
+    }
+}
+
+/// The numeric part of a `Value`, always held in SI base units.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Magnitude {
+    Scalar(f64),
+    Vector(Vec<f64>),
+}
+
+impl Magnitude {
+    /// Applies `operation` elementwise, extending a scalar to the length of
+    /// the other operand's vector.
+    pub fn broadcast(self, rhs: Self, operation: fn(f64, f64) -> f64) -> Result<Self, CrunchError> {
+        Ok(match (self, rhs) {
+            (Magnitude::Scalar(lhs), Magnitude::Scalar(rhs)) => {
+                Magnitude::Scalar(operation(lhs, rhs))
+            }
+            (Magnitude::Scalar(lhs), Magnitude::Vector(rhs)) => {
+                Magnitude::Vector(rhs.into_iter().map(|x| operation(lhs, x)).collect())
+            }
+            (Magnitude::Vector(lhs), Magnitude::Scalar(rhs)) => {
+                Magnitude::Vector(lhs.into_iter().map(|x| operation(x, rhs)).collect())
+            }
+            (Magnitude::Vector(lhs), Magnitude::Vector(rhs)) => {
+                if lhs.len() != rhs.len() {
+                    return Err(CrunchError::LengthMismatch {
+                        lhs: lhs.len(),
+                        rhs: rhs.len(),
+                    });
+                }
+                Magnitude::Vector(
+                    lhs.into_iter()
+                        .zip(rhs)
+                        .map(|(left_x, right_x)| operation(left_x, right_x))
+                        .collect(),
+                )
+            }
+        })
+    }
+
+    pub fn map(self, operation: impl Fn(f64) -> f64) -> Self {
+        match self {
+            Magnitude::Scalar(x) => Magnitude::Scalar(operation(x)),
+            Magnitude::Vector(xs) => Magnitude::Vector(xs.into_iter().map(operation).collect()),
+        }
+    }
+
+    fn contains_zero(&self) -> bool {
+        match self {
+            Magnitude::Scalar(x) => *x == 0.0,
+            Magnitude::Vector(xs) => xs.contains(&0.0),
+        }
+    }
+}
+
+/// Writes a number in full, or in exponent notation when it is very large or
+/// very small, e.g. `6.62607015e-34` rather than a long run of zeros.
+fn format_number(x: f64) -> String {
+    let exponent = x != 0.0 && x.is_finite() && !(1e-6..1e15).contains(&x.abs());
+    match exponent {
+        true => format!("{:e}", x),
+        false => x.to_string(),
+    }
+}
+
+impl fmt::Display for Magnitude {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Magnitude::Scalar(x) => write!(f, "{}", format_number(*x)),
+            Magnitude::Vector(xs) => {
+                let elements: Vec<String> = xs.iter().map(|x| format_number(*x)).collect();
+                write!(f, "[{}]", elements.join(" "))
+            }
+        }
+    }
+}
+
+/// The unit a value was converted to with `->` or `in`, which it is shown in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayUnit {
+    pub expression: UnitExpression,
+    pub unit: Unit,
+}
+
+/// A scalar or vector quantity in SI base units, together with its dimension.
 #[derive(Debug, Clone, PartialEq)]
-pub enum Value {
-    Length(Length),
-    // Mass(Mass),
-    // Time(Time),
-    // Area(Area),
-    VectorLength(Vec<Length>),
-    // VectorMass(Vec<Mass>),
-    // VectorTime(Vec<Time>),
-    // VectorArea(Vec<Area>),
+pub struct Value {
+    pub magnitude: Magnitude,
+    pub dimension: Dimension,
+    /// Whether this is an absolute temperature rather than a difference.
+    /// Absolute temperatures cannot be added, and subtracting one from
+    /// another gives a difference.
+    pub absolute: bool,
+    /// Set by a conversion; arithmetic on the value drops it again.
+    pub display_unit: Option<DisplayUnit>,
 }
 
 impl Value {
     pub fn new(value: f64, unit: Unit) -> Self {
-        match unit {
-            Unit::Meter => Self::Length(Length::new::<meter>(value)),
-            Unit::Kilometer => Self::Length(Length::new::<kilometer>(value)),
-            _ => panic!("Unsupported unit"),
-        }
+        Value::from_unit(Magnitude::Scalar(value), unit)
     }
 
     pub fn new_vec(values: Vec<f64>, unit: Unit) -> Self {
-        match unit {
-            Unit::Meter => Self::VectorLength(
-                values
-                    .into_iter()
-                    .map(|v| Length::new::<meter>(v))
-                    .collect(),
-            ),
-            Unit::Kilometer => Self::VectorLength(
-                values
-                    .into_iter()
-                    .map(|v| Length::new::<kilometer>(v))
-                    .collect(),
+        Value::from_unit(Magnitude::Vector(values), unit)
+    }
+
+    /// A value whose magnitude is already in SI base units.
+    pub fn from_si(magnitude: Magnitude, dimension: Dimension) -> Self {
+        Value {
+            magnitude,
+            dimension,
+            absolute: false,
+            display_unit: None,
+        }
+    }
+
+    fn from_unit(magnitude: Magnitude, unit: Unit) -> Self {
+        let offset = unit.offset.unwrap_or(0.0);
+        Value {
+            absolute: unit.offset.is_some(),
+            ..Value::from_si(
+                magnitude.map(|x| x * unit.conversion_factor + offset),
+                unit.dimension,
+            )
+        }
+    }
+
+    /// Rejects a magnitude which is not a real number, e.g. of `acos(2)`, or
+    /// which is infinite, e.g. of `0[m] ^ -1`.
+    pub fn finite(self) -> Result<Self, CrunchError> {
+        let xs = match &self.magnitude {
+            Magnitude::Scalar(x) => std::slice::from_ref(x),
+            Magnitude::Vector(xs) => xs.as_slice(),
+        };
+        if xs.iter().any(|x| x.is_nan()) {
+            return Err(CrunchError::Domain(
+                "the result is not a real number".to_string(),
+            ));
+        }
+        if xs.iter().any(|x| x.is_infinite()) {
+            return Err(CrunchError::Domain("the result is infinite".to_string()));
+        }
+        Ok(self)
+    }
+
+    /// Raises the value to a power, e.g. `1/2` for a square root. An absolute
+    /// temperature is already in kelvin, so the result is a plain quantity.
+    pub fn pow(self, power: Rational) -> Result<Self, CrunchError> {
+        let dimension = self
+            .dimension
+            .checked_pow(power)
+            .ok_or_else(CrunchError::power_out_of_range)?;
+        Ok(Value::from_si(
+            self.magnitude.map(|x| pow(x, power)),
+            dimension,
+        ))
+    }
+
+    /// Marks the value to be shown in `expression`, which `unit` was resolved
+    /// from. Fails if the unit measures a different dimension, or if only one
+    /// of the value and the unit is an absolute temperature.
+    pub fn convert(self, expression: UnitExpression, unit: Unit) -> Result<Self, CrunchError> {
+        if unit.dimension != self.dimension {
+            return Err(CrunchError::Conversion {
+                from: self.dimension,
+                to: expression,
+            });
+        }
+        match (self.absolute, unit.offset.is_some()) {
+            (true, false) => {
+                return Err(CrunchError::Temperature(format!(
+                    "cannot convert an absolute temperature to [{}], use a scale such as [degC]",
+                    expression
+                )))
+            }
+            (false, true) => {
+                return Err(CrunchError::Temperature(format!(
+                    "cannot convert a temperature difference to [{}], use a difference such as [delta_degC]",
+                    expression
+                )))
+            }
+            _ => {}
+        }
+        Ok(Value {
+            display_unit: Some(DisplayUnit { expression, unit }),
+            ..self
+        })
+    }
+
+    /// The magnitude and unit the value is shown in: its display unit if it
+    /// has one, otherwise SI units.
+    pub fn displayed(&self) -> (Magnitude, String) {
+        match &self.display_unit {
+            Some(DisplayUnit { expression, unit }) => (
+                self.magnitude
+                    .clone()
+                    .map(|x| (x - unit.offset.unwrap_or(0.0)) / unit.conversion_factor),
+                expression.to_string(),
             ),
-            _ => panic!("Unsupported unit"),
+            None => (self.magnitude.clone(), self.dimension.canonical()),
+        }
+    }
+}
+
+/// Writes the value in its display unit or SI units, e.g. `[1 2 3] m^2`, `4 J`
+/// or `2.5 km`.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (magnitude, unit) = self.displayed();
+        if self.display_unit.is_none() && self.dimension.is_dimensionless() {
+            write!(f, "{}", magnitude)
+        } else {
+            write!(f, "{} {}", magnitude, unit)
         }
     }
 }
 
 impl Add for Value {
-    type Output = Value;
+    type Output = Result<Value, CrunchError>;
 
-    fn add(self, rhs: Self) -> Self {
-        match self {
-            // we are float
-            Value::Length(lhs_value) => match rhs {
-                // they are float
-                Value::Length(rhs_value) => Value::Length(lhs_value + rhs_value),
-                // they are vec
-                Value::VectorLength(rhs_value) => {
-                    Value::VectorLength(scalar_vector_addition(lhs_value, rhs_value))
-                }
-            },
-            // we are vec
-            Value::VectorLength(lhs_value) => match rhs {
-                // they are float
-                Value::Length(rhs_value) => {
-                    Value::VectorLength(scalar_vector_addition(rhs_value, lhs_value))
-                }
-                // they are vec
-                Value::VectorLength(rhs_value) => {
-                    Value::VectorLength(elementwise_vector_addition(lhs_value, rhs_value))
-                }
-            },
+    fn add(self, rhs: Self) -> Self::Output {
+        if self.dimension != rhs.dimension {
+            return Err(DimensionMismatch {
+                operation: BinaryOperation::Add,
+                lhs: self.dimension,
+                rhs: rhs.dimension,
+            }
+            .into());
+        }
+        if self.absolute && rhs.absolute {
+            return Err(CrunchError::Temperature(
+                "cannot add two absolute temperatures".to_string(),
+            ));
         }
+        Ok(Value {
+            absolute: self.absolute || rhs.absolute,
+            ..Value::from_si(
+                self.magnitude.broadcast(rhs.magnitude, |x, y| x + y)?,
+                self.dimension,
+            )
+        })
     }
 }
 
 impl Sub for Value {
-    type Output = Value;
+    type Output = Result<Value, CrunchError>;
 
-    fn sub(self, rhs: Self) -> Self {
-        match self {
-            // we are float
-            Value::Length(lhs_value) => match rhs {
-                // they are float
-                Value::Length(rhs_value) => Value::Length(lhs_value - rhs_value),
-                // they are vec
-                Value::VectorLength(rhs_value) => {
-                    Value::VectorLength(scalar_vector_subtraction(lhs_value, rhs_value))
-                }
-            },
-            // we are vec
-            Value::VectorLength(lhs_value) => match rhs {
-                // they are float
-                Value::Length(rhs_value) => {
-                    Value::VectorLength(scalar_vector_subtraction(rhs_value, lhs_value))
-                }
-                // they are vec
-                Value::VectorLength(rhs_value) => {
-                    Value::VectorLength(elementwise_vector_subtraction(lhs_value, rhs_value))
-                }
-            },
+    fn sub(self, rhs: Self) -> Self::Output {
+        if self.dimension != rhs.dimension {
+            return Err(DimensionMismatch {
+                operation: BinaryOperation::Subtract,
+                lhs: self.dimension,
+                rhs: rhs.dimension,
+            }
+            .into());
         }
+        if rhs.absolute && !self.absolute {
+            return Err(CrunchError::Temperature(
+                "cannot subtract an absolute temperature from a temperature difference".to_string(),
+            ));
+        }
+        // the difference of two absolute temperatures is a difference
+        Ok(Value {
+            absolute: self.absolute && !rhs.absolute,
+            ..Value::from_si(
+                self.magnitude.broadcast(rhs.magnitude, |x, y| x - y)?,
+                self.dimension,
+            )
+        })
     }
 }
 
-impl Mul for Value {
-    type Output = Value;
+impl Neg for Value {
+    type Output = Result<Value, CrunchError>;
 
-    fn mul(self, rhs: Self) -> Self {
-        match self {
-            // we are float
-            Value::Length(lhs_value) => match rhs {
-                // they are float
-                Value::Length(rhs_value) => Value::Length(lhs_value * rhs_value),
-                // they are vec
-                Value::VectorLength(rhs_value) => {
-                    Value::VectorLength(scalar_vector_multiplication(lhs_value, rhs_value))
-                }
-            },
-            // we are vec
-            Value::VectorLength(lhs_value) => match rhs {
-                // they are float
-                Value::Length(rhs_value) => {
-                    Value::VectorLength(scalar_vector_multiplication(rhs_value, lhs_value))
-                }
-                // they are vec
-                Value::VectorLength(rhs_value) => {
-                    Value::VectorLength(elementwise_vector_multiplication(lhs_value, rhs_value))
-                }
-            },
+    /// Negates the value, keeping the unit it is shown in.
+    fn neg(self) -> Self::Output {
+        if self.absolute {
+            return Err(CrunchError::Temperature(
+                "cannot negate an absolute temperature".to_string(),
+            ));
         }
+        Ok(Value {
+            magnitude: self.magnitude.map(|x| -x),
+            ..self
+        })
+    }
+}
+
+impl Mul for Value {
+    type Output = Result<Value, CrunchError>;
+
+    /// Absolute temperatures are kept in kelvin, so e.g. `const.k_B * 300[K]`
+    /// multiplies by the thermodynamic temperature, and the product is a
+    /// plain quantity.
+    fn mul(self, rhs: Self) -> Self::Output {
+        let dimension = self
+            .dimension
+            .checked_mul(rhs.dimension)
+            .ok_or_else(CrunchError::power_out_of_range)?;
+        Ok(Value::from_si(
+            self.magnitude.broadcast(rhs.magnitude, |x, y| x * y)?,
+            dimension,
+        ))
     }
 }
 
 impl Div for Value {
-    type Output = Value;
+    type Output = Result<Value, CrunchError>;
 
-    fn div(self, rhs: Self) -> Self {
-        match self {
-            // we are float
-            Value::Length(lhs_value) => match rhs {
-                // they are float
-                Value::Length(rhs_value) => Value::Length(lhs_value / rhs_value),
-                // they are vec
-                Value::VectorLength(rhs_value) => {
-                    Value::VectorLength(scalar_vector_division(lhs_value, rhs_value))
-                }
-            },
-            // we are vec
-            Value::VectorLength(lhs_value) => match rhs {
-                // they are float
-                Value::Length(rhs_value) => {
-                    Value::VectorLength(scalar_vector_division(rhs_value, lhs_value))
-                }
-                // they are vec
-                Value::VectorLength(rhs_value) => {
-                    Value::VectorLength(elementwise_vector_division(lhs_value, rhs_value))
-                }
-            },
+    fn div(self, rhs: Self) -> Self::Output {
+        if rhs.magnitude.contains_zero() {
+            return Err(CrunchError::DivisionByZero);
         }
+        let dimension = self
+            .dimension
+            .checked_div(rhs.dimension)
+            .ok_or_else(CrunchError::power_out_of_range)?;
+        Ok(Value::from_si(
+            self.magnitude.broadcast(rhs.magnitude, |x, y| x / y)?,
+            dimension,
+        ))
     }
 }
 
@@ -303,11 +842,11 @@ pub enum AstNode {
     Print(Box<AstNode>),
     Double {
         value: f64,
-        unit: Unit,
+        unit: UnitExpression,
     },
     Vector {
         value: Vec<f64>,
-        unit: Unit,
+        unit: UnitExpression,
     },
     Name(String),
     Expression {
@@ -319,4 +858,314 @@ pub enum AstNode {
         name: Box<AstNode>,
         expr: Box<AstNode>,
     },
+    /// `override const.c = expr;`, replacing a physical constant.
+    ConstantOverride {
+        name: String,
+        expr: Box<AstNode>,
+    },
+    /// `-expr` or `+expr`.
+    Unary {
+        operation: UnaryOperation,
+        expr: Box<AstNode>,
+    },
+    /// A call of a built in function, e.g. `sin(x)` or `atan2(y, x)`.
+    Call {
+        function: String,
+        arguments: Vec<AstNode>,
+    },
+    /// `expr -> [unit]` or `expr in [unit]`.
+    Conversion {
+        expr: Box<AstNode>,
+        unit: UnitExpression,
+    },
+    /// `dimension Currency;`
+    DimensionDeclaration(String),
+    /// `unit USD : Currency;`, the base unit of a declared dimension.
+    BaseUnitDeclaration {
+        name: String,
+        dimension: String,
+        prefixable: bool,
+    },
+    /// `unit EUR = 1.08[USD];`, or `prefixable unit ...` to allow `kEUR`.
+    UnitDeclaration {
+        name: String,
+        value: Box<AstNode>,
+        prefixable: bool,
+    },
+}
+
+#[test]
+fn test_value_dimensions() {
+    let kilometers = Unit::new(1000.0, Dimension::length());
+    let meters = Unit::new(1.0, Dimension::length());
+    let seconds = Unit::new(1.0, Dimension::time());
+
+    let area = (Value::new(1.0, kilometers.clone()) * Value::new(2.0, kilometers)).unwrap();
+    assert_eq!(area.magnitude, Magnitude::Scalar(2_000_000.0));
+    assert_eq!(area.dimension, Dimension::length().powi(2));
+
+    let speed =
+        (Value::new_vec(vec![10.0, 20.0], meters.clone()) / Value::new(2.0, seconds)).unwrap();
+    assert_eq!(speed.magnitude, Magnitude::Vector(vec![5.0, 10.0]));
+    assert_eq!(speed.dimension, Dimension::length() / Dimension::time());
+    assert_eq!(speed.dimension.to_string(), "m/s");
+
+    let ratio = (Value::new(1.0, meters.clone()) / Value::new_vec(vec![2.0, 4.0], meters)).unwrap();
+    assert_eq!(ratio.magnitude, Magnitude::Vector(vec![0.5, 0.25]));
+    assert!(ratio.dimension.is_dimensionless());
+}
+
+#[test]
+fn test_dimension_mismatch() {
+    let meters = Unit::new(1.0, Dimension::length());
+    let area = Value::new(1.0, meters.clone().powi(2));
+
+    let sum = area.clone() + Value::new(2.0, meters.clone().powi(2));
+    assert_eq!(sum.unwrap().magnitude, Magnitude::Scalar(3.0));
+
+    let error = (area.clone() + Value::new(2.0, meters)).unwrap_err();
+    assert_eq!(
+        error,
+        DimensionMismatch {
+            operation: BinaryOperation::Add,
+            lhs: Dimension::length().powi(2),
+            rhs: Dimension::length(),
+        }
+        .into()
+    );
+    assert_eq!(error.to_string(), "cannot add Area (m^2) to Length (m)");
+
+    let error = (area - Value::new(2.0, Unit::new(1.0, Dimension::time()))).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "cannot subtract Time (s) from Area (m^2)"
+    );
+}
+
+#[test]
+fn test_value_errors() {
+    let meters = Unit::new(1.0, Dimension::length());
+
+    assert_eq!(
+        Value::new_vec(vec![1.0, 2.0], meters.clone())
+            + Value::new_vec(vec![1.0, 2.0, 3.0], meters.clone()),
+        Err(CrunchError::LengthMismatch { lhs: 2, rhs: 3 })
+    );
+    assert_eq!(
+        Value::new(1.0, meters.clone()) / Value::new_vec(vec![1.0, 0.0], meters),
+        Err(CrunchError::DivisionByZero)
+    );
+}
+
+#[test]
+fn test_value_display() {
+    let meters = Unit::new(1.0, Dimension::length());
+
+    assert_eq!(
+        Value::new(2.5, meters.clone().powi(2)).to_string(),
+        "2.5 m^2"
+    );
+    assert_eq!(
+        Value::new_vec(vec![1.0, 2.0, 3.0], meters).to_string(),
+        "[1 2 3] m"
+    );
+    assert_eq!(
+        Value::new(4.0, Unit::new(1.0, Dimension::dimensionless())).to_string(),
+        "4"
+    );
+
+    // very large and very small numbers use exponent notation
+    let number = |x| Magnitude::Scalar(x).to_string();
+    assert_eq!(number(6.62607015e-34), "6.62607015e-34");
+    assert_eq!(number(6.02214076e23), "6.02214076e23");
+    assert_eq!(number(-1e15), "-1e15");
+    assert_eq!(number(999999999999999.0), "999999999999999");
+    assert_eq!(number(0.000001), "0.000001");
+    assert_eq!(number(0.0), "0");
+    assert_eq!(Magnitude::Vector(vec![1e-9, 2.0]).to_string(), "[1e-9 2]");
+}
+
+#[test]
+fn test_canonical_display() {
+    let meters_per_second = Unit::new(1.0, Dimension::length() / Dimension::time());
+    let speed = Value::new(3.0, meters_per_second);
+    let squared = (speed.clone() * speed).unwrap();
+    assert_eq!(squared.to_string(), "9 m^2/s^2");
+
+    let kilograms = Unit::new(1.0, Dimension::mass());
+    let energy = (Value::new(2.0, kilograms) * squared).unwrap();
+    assert_eq!(energy.to_string(), "18 J");
+    assert_eq!(energy.dimension.to_string(), "kg*m^2/s^2");
+
+    let force = (energy.clone() / Value::new(3.0, Unit::new(1.0, Dimension::length()))).unwrap();
+    assert_eq!(force.to_string(), "6 N");
+
+    // no named unit fits exactly, so the base units are kept
+    let per_kelvin = Value::new(1.0, Unit::new(1.0, Dimension::temperature()));
+    assert_eq!(
+        (energy / per_kelvin).unwrap().to_string(),
+        "18 kg*m^2/(s^2*K)"
+    );
+    assert_eq!(
+        Value::new(50.0, Unit::new(1.0, Dimension::time().powi(-1))).to_string(),
+        "50 1/s"
+    );
+}
+
+#[test]
+fn test_unit_expression_display() {
+    let watts_per_square_meter_kelvin =
+        UnitExpression::new("W", 1) / (UnitExpression::new("m", 2) * UnitExpression::new("K", 1));
+    assert_eq!(
+        watts_per_square_meter_kelvin,
+        UnitExpression::product(&[("W", 1), ("m", -2), ("K", -1)])
+    );
+    assert_eq!(watts_per_square_meter_kelvin.to_string(), "W/(m^2*K)");
+    assert_eq!(
+        UnitExpression::product(&[("kg", 1), ("m", 1), ("s", -2)]).to_string(),
+        "kg*m/s^2"
+    );
+    assert_eq!(UnitExpression::new("Hz", 1).powi(-1).to_string(), "1/Hz");
+}
+
+#[test]
+fn test_dimension_display() {
+    let force = Dimension::mass() * Dimension::length() / Dimension::time().powi(2);
+    assert_eq!(force.to_string(), "kg*m/s^2");
+    assert_eq!(Dimension::time().powi(-1).to_string(), "1/s");
+    assert_eq!(Dimension::dimensionless().to_string(), "1");
+    assert_eq!(
+        (Dimension::mass() / Dimension::time().powi(3) / Dimension::temperature()).to_string(),
+        "kg/(s^3*K)"
+    );
+}
+
+#[test]
+fn test_rational_exponents() {
+    let half = Rational::new(1, 2).unwrap();
+    let meters = Unit::new(1.0, Dimension::length());
+    let hertz = Unit::new(1.0, Dimension::time().powi(-1));
+
+    let side = Value::new(9.0, meters.clone().powi(2)).pow(half).unwrap();
+    assert_eq!(side, Value::new(3.0, meters));
+
+    let noise = Value::new_vec(vec![4.0, 16.0], hertz).pow(half).unwrap();
+    assert_eq!(noise.magnitude, Magnitude::Vector(vec![2.0, 4.0]));
+    assert_eq!(noise.dimension, Dimension::time().pow(-half));
+    assert_eq!(noise.dimension.to_string(), "1/s^(1/2)");
+    assert_eq!(
+        (noise.dimension.clone() * noise.dimension).to_string(),
+        "1/s"
+    );
+
+    let kilometers = Unit::new(1e3, Dimension::length());
+    assert_eq!(
+        kilometers.pow(-half),
+        Unit::new(1e3_f64.powf(-0.5), Dimension::length().pow(-half))
+    );
+    assert_eq!(
+        (UnitExpression::new("MPa", 1) * UnitExpression::new("m", 1).pow(half)).to_string(),
+        "MPa*m^(1/2)"
+    );
+}
+
+#[test]
+fn test_value_conversion() {
+    let meters = Unit::new(1.0, Dimension::length());
+    let kilometers = Unit::new(1e3, Dimension::length());
+
+    let distance = Value::new(2500.0, meters.clone())
+        .convert(UnitExpression::new("km", 1), kilometers.clone())
+        .unwrap();
+    assert_eq!(distance.magnitude, Magnitude::Scalar(2500.0));
+    assert_eq!(distance.to_string(), "2.5 km");
+
+    let distances = Value::new_vec(vec![500.0, 1500.0], meters.clone())
+        .convert(UnitExpression::new("km", 1), kilometers)
+        .unwrap();
+    assert_eq!(distances.to_string(), "[0.5 1.5] km");
+
+    // arithmetic goes back to SI units
+    assert_eq!((distances + distance).unwrap().to_string(), "[3000 4000] m");
+
+    assert_eq!(
+        Value::new(5.0, meters)
+            .convert(
+                UnitExpression::new("s", 1),
+                Unit::new(1.0, Dimension::time())
+            )
+            .unwrap_err()
+            .to_string(),
+        "cannot convert Length (m) to [s]"
+    );
+}
+
+#[test]
+fn test_absolute_temperatures() {
+    let celsius = Unit::absolute(1.0, 273.15);
+    let fahrenheit = Unit::absolute(5.0 / 9.0, 459.67 * 5.0 / 9.0);
+    let kelvin = Unit::absolute(1.0, 0.0);
+    let difference = Unit::new(1.0, Dimension::temperature());
+
+    let freezing = Value::new(0.0, celsius.clone());
+    assert_eq!(freezing.magnitude, Magnitude::Scalar(273.15));
+    assert!(freezing.absolute);
+
+    // absolute + difference and absolute - absolute
+    let warmer = (freezing.clone() + Value::new(20.0, difference.clone())).unwrap();
+    assert!(warmer.absolute);
+    let rise = (warmer.clone() - freezing.clone()).unwrap();
+    assert!(!rise.absolute);
+    assert_eq!(rise.to_string(), "20 K");
+    assert_eq!(
+        (freezing.clone() + Value::new(1.0, kelvin.clone())).unwrap_err(),
+        CrunchError::Temperature("cannot add two absolute temperatures".to_string())
+    );
+    assert!((rise.clone() - warmer.clone()).is_err());
+
+    // products, quotients and powers use the thermodynamic temperature
+    let two = Value::new(2.0, Unit::new(1.0, Dimension::dimensionless()));
+    let doubled = (freezing.clone() * two.clone()).unwrap();
+    assert!(!doubled.absolute);
+    assert_eq!(doubled.to_string(), "546.3 K");
+    assert!(!(two / freezing.clone()).unwrap().absolute);
+    assert!(!freezing.clone().pow(Rational::from(2)).unwrap().absolute);
+    assert_eq!(
+        (rise.clone() * rise.clone()).unwrap().to_string(),
+        "400 K^2"
+    );
+
+    // conversions take the offset into account, for vectors too
+    let shown = |value: Value, alias: &str, unit: Unit| {
+        value
+            .convert(UnitExpression::new(alias, 1), unit)
+            .map(|value| value.to_string())
+    };
+    assert_eq!(
+        shown(
+            Value::new_vec(vec![32.0, 212.0], fahrenheit.clone()),
+            "degC",
+            celsius.clone()
+        ),
+        Ok("[0 100] degC".to_string())
+    );
+    assert_eq!(
+        shown(Value::new(300.0, kelvin.clone()), "degF", fahrenheit),
+        Ok("80.33000000000003 degF".to_string())
+    );
+    assert_eq!(
+        shown(
+            rise.clone(),
+            "delta_degF",
+            Unit::new(5.0 / 9.0, Dimension::temperature())
+        ),
+        Ok("36 delta_degF".to_string())
+    );
+    assert!(shown(rise, "degC", celsius.clone()).is_err());
+    assert!(shown(freezing, "delta_degC", difference).is_err());
+
+    // in a compound unit a temperature scale measures differences
+    assert_eq!(celsius.clone().powi(1), celsius);
+    assert_eq!(celsius.powi(2).offset, None);
+    assert_eq!((Unit::new(1.0, Dimension::time()) * kelvin).offset, None);
 }
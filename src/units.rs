@@ -0,0 +1,700 @@
+use super::error::CrunchError;
+use super::suggest;
+use super::types::*;
+
+/// An SI prefix, e.g. `k`/`kilo` for 1e3.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Prefix {
+    pub symbol: &'static str,
+    pub name: &'static str,
+    pub factor: f64,
+}
+
+const fn prefix(symbol: &'static str, name: &'static str, factor: f64) -> Prefix {
+    Prefix {
+        symbol,
+        name,
+        factor,
+    }
+}
+
+/// Every SI prefix from quecto to quetta. `u`, `µ` (micro sign) and `μ` (Greek
+/// mu) all mean micro.
+pub const PREFIXES: [Prefix; 26] = [
+    prefix("q", "quecto", 1e-30),
+    prefix("r", "ronto", 1e-27),
+    prefix("y", "yocto", 1e-24),
+    prefix("z", "zepto", 1e-21),
+    prefix("a", "atto", 1e-18),
+    prefix("f", "femto", 1e-15),
+    prefix("p", "pico", 1e-12),
+    prefix("n", "nano", 1e-9),
+    prefix("µ", "micro", 1e-6),
+    prefix("μ", "micro", 1e-6),
+    prefix("u", "micro", 1e-6),
+    prefix("m", "milli", 1e-3),
+    prefix("c", "centi", 1e-2),
+    prefix("d", "deci", 1e-1),
+    prefix("da", "deca", 1e1),
+    prefix("h", "hecto", 1e2),
+    prefix("k", "kilo", 1e3),
+    prefix("M", "mega", 1e6),
+    prefix("G", "giga", 1e9),
+    prefix("T", "tera", 1e12),
+    prefix("P", "peta", 1e15),
+    prefix("E", "exa", 1e18),
+    prefix("Z", "zetta", 1e21),
+    prefix("Y", "yotta", 1e24),
+    prefix("R", "ronna", 1e27),
+    prefix("Q", "quetta", 1e30),
+];
+
+/// A named unit which can appear in a `[unit^n]` annotation.
+#[derive(PartialEq, Debug, Clone)]
+pub struct UnitDefinition {
+    /// The short form, e.g. `m`. Takes symbol prefixes such as `k`.
+    pub symbol: String,
+    /// Long forms, e.g. `meter` and `meters`. Take long prefixes such as `kilo`.
+    pub names: Vec<String>,
+    pub unit: Unit,
+    /// Whether SI prefixes may be attached to this unit.
+    pub prefixable: bool,
+}
+
+impl UnitDefinition {
+    pub fn new(symbol: &str, names: &[&str], unit: Unit, prefixable: bool) -> Self {
+        UnitDefinition {
+            symbol: symbol.to_string(),
+            names: names.iter().map(|name| name.to_string()).collect(),
+            unit,
+            prefixable,
+        }
+    }
+}
+
+/// The units known to the interpreter.
+///
+/// An alias is looked up in two steps, so that the result never depends on
+/// the order units were defined in:
+///
+/// 1. an exact, case sensitive match on a symbol or long name wins, so `min`
+///    is always the minute and never milli-inch;
+/// 2. otherwise the alias is split into a prefix and a prefixable unit, symbol
+///    prefixes going with symbols (`km`) and long prefixes with long names
+///    (`kilometers`). Case matters, so `mm` is a millimeter and `Mm` a
+///    megameter. Longer prefixes are tried first, so `dam` is a decameter.
+#[derive(PartialEq, Debug, Clone)]
+pub struct UnitRegistry {
+    definitions: Vec<UnitDefinition>,
+    /// Base dimensions declared by the program, with the symbol of their base
+    /// unit once one is declared.
+    dimensions: Vec<(String, Option<String>)>,
+}
+
+impl UnitRegistry {
+    pub fn empty() -> Self {
+        UnitRegistry {
+            definitions: vec![],
+            dimensions: vec![],
+        }
+    }
+
+    /// The SI units built into crunch.
+    pub fn si() -> Self {
+        let length = Dimension::length;
+        let mass = Dimension::mass;
+        let time = Dimension::time;
+        let current = Dimension::current;
+        let force = || mass() * length() / time().powi(2);
+        let energy = || force() * length();
+        let power = || energy() / time();
+        let charge = || current() * time();
+        let voltage = || power() / current();
+
+        let mut registry = UnitRegistry::empty();
+        let mut define = |symbol, names: &[&str], factor, dimension, prefixable| {
+            registry.define(UnitDefinition::new(
+                symbol,
+                names,
+                Unit::new(factor, dimension),
+                prefixable,
+            ))
+        };
+
+        // base units; the kilogram is prefixed from the gram
+        define("m", &["meter", "meters"], 1.0, length(), true);
+        define("g", &["gram", "grams"], 1e-3, mass(), true);
+        define("s", &["second", "seconds"], 1.0, time(), true);
+        define(
+            "A",
+            &["ampere", "amperes", "amp", "amps"],
+            1.0,
+            current(),
+            true,
+        );
+        define("mol", &["mole", "moles"], 1.0, Dimension::amount(), true);
+        define(
+            "cd",
+            &["candela", "candelas"],
+            1.0,
+            Dimension::luminous_intensity(),
+            true,
+        );
+
+        // temperatures are absolute when written on their own, `[K]`, and
+        // differences otherwise, as in `[J/K]`
+        let temperature = Dimension::temperature;
+        let mut define_scale = |symbol, names: &[&str], factor, offset, prefixable| {
+            registry.define(UnitDefinition::new(
+                symbol,
+                names,
+                Unit::absolute(factor, offset),
+                prefixable,
+            ))
+        };
+        define_scale("K", &["kelvin"], 1.0, 0.0, true);
+        define_scale("degC", &["celsius"], 1.0, 273.15, false);
+        define_scale(
+            "degF",
+            &["fahrenheit"],
+            5.0 / 9.0,
+            459.67 * 5.0 / 9.0,
+            false,
+        );
+
+        let mut define = |symbol, names: &[&str], factor, dimension, prefixable| {
+            registry.define(UnitDefinition::new(
+                symbol,
+                names,
+                Unit::new(factor, dimension),
+                prefixable,
+            ))
+        };
+        define("delta_K", &["delta_kelvin"], 1.0, temperature(), false);
+        define("delta_degC", &["delta_celsius"], 1.0, temperature(), false);
+        define(
+            "delta_degF",
+            &["delta_fahrenheit"],
+            5.0 / 9.0,
+            temperature(),
+            false,
+        );
+
+        // time units outside the SI which are not prefixed
+        define("min", &["minute", "minutes"], 60.0, time(), false);
+        define("h", &["hour", "hours"], 3600.0, time(), false);
+        define("d", &["day", "days"], 86400.0, time(), false);
+        define("wk", &["week", "weeks"], 604800.0, time(), false);
+        // the Julian year, and a twelfth of it
+        define("mo", &["month", "months"], 2629800.0, time(), false);
+        define("yr", &["year", "years"], 31557600.0, time(), false);
+
+        // named derived units
+        define("Hz", &["hertz"], 1.0, time().powi(-1), true);
+        define("N", &["newton", "newtons"], 1.0, force(), true);
+        define(
+            "Pa",
+            &["pascal", "pascals"],
+            1.0,
+            force() / length().powi(2),
+            true,
+        );
+        define("J", &["joule", "joules"], 1.0, energy(), true);
+        define("W", &["watt", "watts"], 1.0, power(), true);
+        define("C", &["coulomb", "coulombs"], 1.0, charge(), true);
+        define("V", &["volt", "volts"], 1.0, voltage(), true);
+        define("F", &["farad", "farads"], 1.0, charge() / voltage(), true);
+        define("Ω", &["ohm", "ohms"], 1.0, voltage() / current(), true);
+        define("Wh", &["watthour", "watthours"], 3600.0, energy(), true);
+        define(
+            "L",
+            &["liter", "liters", "litre", "litres"],
+            1e-3,
+            length().powi(3),
+            true,
+        );
+        // not prefixable, so that `pt`, `qt` and `mt` are not read as tonnes
+        define("t", &["tonne", "tonnes"], 1e3, mass(), false);
+
+        // angles, a dimension of their own with the radian as base unit
+        let angle = Dimension::angle;
+        let turn = 2.0 * std::f64::consts::PI;
+        define("rad", &["radian", "radians"], 1.0, angle(), true);
+        define("deg", &["degree", "degrees"], turn / 360.0, angle(), false);
+        define(
+            "arcmin",
+            &["arcminute", "arcminutes"],
+            turn / 21600.0,
+            angle(),
+            false,
+        );
+        define(
+            "arcsec",
+            &["arcsecond", "arcseconds"],
+            turn / 1296000.0,
+            angle(),
+            false,
+        );
+        define(
+            "turn",
+            &["turns", "revolution", "revolutions"],
+            turn,
+            angle(),
+            false,
+        );
+        define(
+            "grad",
+            &["gradian", "gradians", "gon"],
+            turn / 400.0,
+            angle(),
+            false,
+        );
+
+        registry
+            .dimensions
+            .push(("Angle".to_string(), Some("rad".to_string())));
+        registry
+    }
+
+    /// The SI units together with the US customary and imperial units.
+    pub fn builtin() -> Self {
+        let mut registry = UnitRegistry::si();
+        registry.define_customary();
+        registry
+    }
+
+    /// US customary and imperial units, none of which take prefixes. Volumes
+    /// which differ between the two systems have no unqualified symbol: a
+    /// gallon is either `usgal` or `impgal`.
+    fn define_customary(&mut self) {
+        let length = Dimension::length;
+        let volume = || length().powi(3);
+        let force = || Dimension::mass() * length() / Dimension::time().powi(2);
+
+        // exact by definition, in SI units
+        let inch = 0.0254;
+        let foot = 0.3048;
+        let pound = 0.45359237;
+        let pound_force = 4.4482216152605;
+        let us_gallon = 3.785411784e-3;
+        let imperial_gallon = 4.54609e-3;
+
+        let mut define = |symbol, names: &[&str], factor, dimension| {
+            self.define(UnitDefinition::new(
+                symbol,
+                names,
+                Unit::new(factor, dimension),
+                false,
+            ))
+        };
+
+        define("in", &["inch", "inches"], inch, length());
+        define("ft", &["foot", "feet"], foot, length());
+        define("yd", &["yard", "yards"], 0.9144, length());
+        define("mi", &["mile", "miles"], 1609.344, length());
+        // the height of one slot in a 19 inch equipment rack
+        define("U", &["rackunit", "rackunits"], 1.75 * inch, length());
+
+        define("lb", &["lbs", "pound", "pounds"], pound, Dimension::mass());
+        define("oz", &["ounce", "ounces"], pound / 16.0, Dimension::mass());
+        define("lbf", &["poundforce"], pound_force, force());
+
+        define("usgal", &["usgallon", "usgallons"], us_gallon, volume());
+        define("usqt", &["usquart", "usquarts"], us_gallon / 4.0, volume());
+        define("uspt", &["uspint", "uspints"], us_gallon / 8.0, volume());
+        define(
+            "usfloz",
+            &["usfluidounce", "usfluidounces"],
+            us_gallon / 128.0,
+            volume(),
+        );
+        define(
+            "impgal",
+            &["impgallon", "impgallons"],
+            imperial_gallon,
+            volume(),
+        );
+        define(
+            "impqt",
+            &["impquart", "impquarts"],
+            imperial_gallon / 4.0,
+            volume(),
+        );
+        define(
+            "imppt",
+            &["imppint", "imppints"],
+            imperial_gallon / 8.0,
+            volume(),
+        );
+        define(
+            "impfloz",
+            &["impfluidounce", "impfluidounces"],
+            imperial_gallon / 160.0,
+            volume(),
+        );
+
+        define(
+            "BTU",
+            &["Btu", "btu", "britishthermalunit", "britishthermalunits"],
+            1055.05585262,
+            force() * length(),
+        );
+        define(
+            "psi",
+            &[],
+            pound_force / (inch * inch),
+            force() / length().powi(2),
+        );
+        define(
+            "hp",
+            &["horsepower"],
+            550.0 * foot * pound_force,
+            force() * length() / Dimension::time(),
+        );
+    }
+
+    /// Adds a unit, replacing any existing unit with the same symbol.
+    pub fn define(&mut self, definition: UnitDefinition) {
+        self.definitions
+            .retain(|existing| existing.symbol != definition.symbol);
+        self.definitions.push(definition);
+    }
+
+    /// Declares a new base dimension, e.g. `Currency`.
+    pub fn declare_dimension(&mut self, name: &str) -> Result<(), CrunchError> {
+        if self.dimensions.iter().any(|(declared, _)| declared == name) {
+            return Err(CrunchError::Declaration(format!(
+                "dimension `{}` is already declared",
+                name
+            )));
+        }
+        self.dimensions.push((name.to_string(), None));
+        Ok(())
+    }
+
+    /// Declares `symbol` as the base unit of the declared dimension `dimension`.
+    pub fn declare_base_unit(
+        &mut self,
+        symbol: &str,
+        dimension: &str,
+        prefixable: bool,
+    ) -> Result<(), CrunchError> {
+        self.check_undefined(symbol)?;
+        let base_unit = match self
+            .dimensions
+            .iter_mut()
+            .find(|(declared, _)| declared == dimension)
+        {
+            Some((_, base_unit)) => base_unit,
+            None => return Err(CrunchError::UnknownDimension(dimension.to_string())),
+        };
+        if let Some(existing) = base_unit {
+            return Err(CrunchError::Declaration(format!(
+                "dimension `{}` already has the base unit `{}`",
+                dimension, existing
+            )));
+        }
+        *base_unit = Some(symbol.to_string());
+
+        let base = BaseDimension {
+            name: dimension.to_string(),
+            symbol: symbol.to_string(),
+        };
+        self.define(UnitDefinition::new(
+            symbol,
+            &[],
+            Unit::new(1.0, Dimension::declared(base)),
+            prefixable,
+        ));
+        Ok(())
+    }
+
+    /// Declares a unit defined by the program, refusing to shadow an existing
+    /// unit or prefixed unit.
+    pub fn declare_unit(
+        &mut self,
+        symbol: &str,
+        unit: Unit,
+        prefixable: bool,
+    ) -> Result<(), CrunchError> {
+        self.check_undefined(symbol)?;
+        self.define(UnitDefinition::new(symbol, &[], unit, prefixable));
+        Ok(())
+    }
+
+    fn check_undefined(&self, symbol: &str) -> Result<(), CrunchError> {
+        match self.lookup(symbol) {
+            Some(_) => Err(CrunchError::Declaration(format!(
+                "unit `{}` is already defined",
+                symbol
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn exact(&self, alias: &str) -> Option<&UnitDefinition> {
+        self.definitions.iter().find(|definition| {
+            definition.symbol == alias || definition.names.iter().any(|name| name == alias)
+        })
+    }
+
+    fn prefixed(&self, alias: &str) -> Option<Unit> {
+        let mut prefixes: Vec<&Prefix> = PREFIXES.iter().collect();
+        prefixes.sort_by_key(|prefix| std::cmp::Reverse(prefix.symbol.len()));
+
+        for prefix in &prefixes {
+            if let Some(rest) = alias.strip_prefix(prefix.symbol) {
+                let found = self
+                    .definitions
+                    .iter()
+                    .find(|definition| definition.prefixable && definition.symbol == rest);
+                if let Some(definition) = found {
+                    return Some(prefix_unit(prefix, definition.unit.clone()));
+                }
+            }
+        }
+        for prefix in PREFIXES.iter() {
+            if let Some(rest) = alias.strip_prefix(prefix.name) {
+                let found = self.definitions.iter().find(|definition| {
+                    definition.prefixable && definition.names.iter().any(|name| name == rest)
+                });
+                if let Some(definition) = found {
+                    return Some(prefix_unit(prefix, definition.unit.clone()));
+                }
+            }
+        }
+        None
+    }
+
+    /// Finds the unit named by `alias`, e.g. `km` or `micrometers`.
+    pub fn lookup(&self, alias: &str) -> Option<Unit> {
+        match self.exact(alias) {
+            Some(definition) => Some(definition.unit.clone()),
+            None => self.prefixed(alias),
+        }
+    }
+
+    /// The known alias nearest to an unknown `alias`, including prefixed
+    /// units, so that `kilometre` suggests `kilometer` and `KPA` suggests
+    /// `kPa`. A volume which differs between US customary and imperial units
+    /// suggests both, `gal` gives `usgal` and `impgal`.
+    pub fn suggest(&self, alias: &str) -> Vec<String> {
+        let (us, imperial) = (format!("us{}", alias), format!("imp{}", alias));
+        if self.exact(&us).is_some() && self.exact(&imperial).is_some() {
+            return vec![us, imperial];
+        }
+
+        let lowercase = alias.to_lowercase();
+        let mut candidates = self.aliases();
+        for prefix in PREFIXES.iter() {
+            let prefixable = self
+                .definitions
+                .iter()
+                .filter(|definition| definition.prefixable);
+            if lowercase.starts_with(&prefix.symbol.to_lowercase()) {
+                candidates.extend(
+                    prefixable
+                        .clone()
+                        .map(|definition| format!("{}{}", prefix.symbol, definition.symbol)),
+                );
+            }
+            if lowercase.starts_with(prefix.name) {
+                candidates.extend(
+                    prefixable
+                        .flat_map(|definition| &definition.names)
+                        .map(|name| format!("{}{}", prefix.name, name)),
+                );
+            }
+        }
+        suggest::closest(alias, candidates.iter().map(String::as_str))
+            .into_iter()
+            .collect()
+    }
+
+    /// Every unprefixed symbol and long name, for completion and listings.
+    pub fn aliases(&self) -> Vec<String> {
+        self.definitions
+            .iter()
+            .flat_map(|definition| {
+                std::iter::once(definition.symbol.clone()).chain(definition.names.clone())
+            })
+            .collect()
+    }
+}
+
+impl Default for UnitRegistry {
+    fn default() -> Self {
+        UnitRegistry::builtin()
+    }
+}
+
+fn prefix_unit(prefix: &Prefix, unit: Unit) -> Unit {
+    Unit {
+        conversion_factor: prefix.factor * unit.conversion_factor,
+        ..unit
+    }
+}
+
+#[test]
+fn test_si_prefixes() {
+    let registry = UnitRegistry::si();
+    let length = |factor| Some(Unit::new(factor, Dimension::length()));
+
+    assert_eq!(registry.lookup("m"), length(1.0));
+    assert_eq!(registry.lookup("meters"), length(1.0));
+    assert_eq!(registry.lookup("km"), length(1e3));
+    assert_eq!(registry.lookup("kilometer"), length(1e3));
+    assert_eq!(registry.lookup("kilometers"), length(1e3));
+    assert_eq!(registry.lookup("mm"), length(1e-3));
+    assert_eq!(registry.lookup("Mm"), length(1e6));
+    assert_eq!(registry.lookup("dam"), length(1e1));
+    assert_eq!(registry.lookup("dm"), length(1e-1));
+    assert_eq!(registry.lookup("µm"), length(1e-6));
+    assert_eq!(registry.lookup("μm"), length(1e-6));
+    assert_eq!(registry.lookup("um"), length(1e-6));
+    assert_eq!(registry.lookup("qm"), length(1e-30));
+    assert_eq!(registry.lookup("Qm"), length(1e30));
+    assert_eq!(registry.lookup("micrometers"), length(1e-6));
+
+    // prefixes only combine with the matching form, and only once
+    assert_eq!(registry.lookup("kmeter"), None);
+    assert_eq!(registry.lookup("kilom"), None);
+    assert_eq!(registry.lookup("kkm"), None);
+    assert_eq!(registry.lookup("KM"), None);
+}
+
+#[test]
+fn test_si_base_and_derived_units() {
+    let registry = UnitRegistry::si();
+    let unit = |factor, dimension| Some(Unit::new(factor, dimension));
+    let energy = Dimension::mass() * Dimension::length().powi(2) / Dimension::time().powi(2);
+
+    assert_eq!(registry.lookup("kg"), unit(1.0, Dimension::mass()));
+    assert_eq!(registry.lookup("kilograms"), unit(1.0, Dimension::mass()));
+    assert_eq!(registry.lookup("mg"), unit(1e-6, Dimension::mass()));
+    assert_eq!(registry.lookup("ms"), unit(1e-3, Dimension::time()));
+    assert_eq!(registry.lookup("min"), unit(60.0, Dimension::time()));
+    assert_eq!(registry.lookup("h"), unit(3600.0, Dimension::time()));
+    assert_eq!(registry.lookup("days"), unit(86400.0, Dimension::time()));
+    assert_eq!(registry.lookup("mA"), unit(1e-3, Dimension::current()));
+    assert_eq!(registry.lookup("K"), Some(Unit::absolute(1.0, 0.0)));
+    assert_eq!(registry.lookup("mK"), Some(Unit::absolute(1e-3, 0.0)));
+    assert_eq!(
+        registry.lookup("celsius"),
+        Some(Unit::absolute(1.0, 273.15))
+    );
+    assert_eq!(
+        registry.lookup("delta_degC"),
+        unit(1.0, Dimension::temperature())
+    );
+    assert_eq!(registry.lookup("mmol"), unit(1e-3, Dimension::amount()));
+    assert_eq!(
+        registry.lookup("cd"),
+        unit(1.0, Dimension::luminous_intensity())
+    );
+    assert_eq!(registry.lookup("kJ"), unit(1e3, energy.clone()));
+    assert_eq!(registry.lookup("kWh"), unit(3.6e6, energy.clone()));
+    assert_eq!(registry.lookup("MW"), unit(1e6, energy / Dimension::time()));
+
+    // `h` and `d` are units in their own right, but still prefixes elsewhere
+    assert_eq!(registry.lookup("hm"), unit(1e2, Dimension::length()));
+    assert_eq!(registry.lookup("dm"), unit(1e-1, Dimension::length()));
+    assert_eq!(registry.lookup("kh"), None);
+}
+
+#[test]
+fn test_exact_units_win_over_prefixes() {
+    let mut registry = UnitRegistry::si();
+    registry.define(UnitDefinition::new(
+        "min",
+        &["minute", "minutes"],
+        Unit::new(60.0, Dimension::time()),
+        false,
+    ));
+    registry.define(UnitDefinition::new(
+        "in",
+        &["inch", "inches"],
+        Unit::new(0.0254, Dimension::length()),
+        true,
+    ));
+
+    assert_eq!(
+        registry.lookup("min"),
+        Some(Unit::new(60.0, Dimension::time()))
+    );
+    assert_eq!(
+        registry.lookup("min"),
+        registry.lookup("minute"),
+        "min is the minute, not a milli-inch"
+    );
+    assert_eq!(
+        registry.lookup("milliinch"),
+        Some(Unit::new(0.0254 * 1e-3, Dimension::length()))
+    );
+    assert_eq!(registry.lookup("kminute"), None);
+}
+
+#[test]
+fn test_customary_units() {
+    let registry = UnitRegistry::builtin();
+    let factor = |alias| registry.lookup(alias).unwrap().conversion_factor;
+    let close = |alias, expected: f64| {
+        let found = factor(alias);
+        assert!(
+            (found - expected).abs() <= expected * 1e-12,
+            "{} is {}, not {}",
+            alias,
+            found,
+            expected
+        );
+    };
+
+    assert_eq!(
+        registry.lookup("feet"),
+        Some(Unit::new(0.3048, Dimension::length()))
+    );
+    close("mi", 5280.0 * factor("ft"));
+    close("yd", 36.0 * factor("in"));
+    assert_eq!(
+        registry.lookup("lbs"),
+        Some(Unit::new(0.45359237, Dimension::mass()))
+    );
+    close("ounces", factor("lb") / 16.0);
+
+    // the US gallon is 231 cubic inches, the imperial gallon exactly 4.54609 L
+    close("usgal", 231.0 * factor("in").powi(3));
+    close("impgallons", 4.54609e-3);
+    close("usfloz", 29.5735295625e-6);
+    close("impfloz", 28.4130625e-6);
+    close("uspt", factor("usqt") / 2.0);
+    assert_eq!(registry.lookup("gal"), None);
+    assert_eq!(
+        registry.suggest("gal"),
+        vec!["usgal".to_string(), "impgal".to_string()]
+    );
+    assert_eq!(
+        registry.suggest("pints"),
+        vec!["uspints".to_string(), "imppints".to_string()]
+    );
+
+    close("BTU", 1055.05585262);
+    close("psi", 6894.757293168361);
+    close("hp", 745.6998715822702);
+    assert_eq!(
+        registry.lookup("psi").unwrap().dimension,
+        registry.lookup("Pa").unwrap().dimension
+    );
+
+    // none of these take SI prefixes, and exact matches still win: `ft` is not a
+    // femtotonne, `min` not a milli-inch
+    assert_eq!(registry.lookup("kft"), None);
+    assert_eq!(factor("ft"), 0.3048);
+    assert_eq!(factor("min"), 60.0);
+    assert_eq!(UnitRegistry::si().lookup("mi"), None);
+
+    // nor does the tonne, so these are not picotonnes, quectotonnes or
+    // millitonnes
+    for alias in ["pt", "qt", "mt", "kt", "kilotonnes"] {
+        assert_eq!(registry.lookup(alias), None, "{}", alias);
+    }
+}
@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Runs the crunch binary with `arguments`.
+fn crunch(arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_crunch"))
+        .args(arguments)
+        .output()
+        .unwrap()
+}
+
+/// Writes `contents` to a file of its own in the temporary directory.
+fn temporary_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("crunch-{}-{}", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn test_eval_negative_expression() {
+    let output = crunch(&["eval", "-2 ^ 2"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "-4\n");
+
+    let output = crunch(&["eval", "-3[m] -> [cm]"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "-300 cm\n");
+}
+
+#[test]
+fn test_units_file_only_declares() {
+    let units = temporary_file("override.units", "override const.c = 1[m/s];\n");
+    let output = crunch(&["--units-file", units.to_str().unwrap(), "eval", "const.c"]);
+    std::fs::remove_file(&units).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        format!(
+            "error: {}:1: a units file can only declare dimensions and units\n",
+            units.display()
+        )
+    );
+}